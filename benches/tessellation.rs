@@ -0,0 +1,130 @@
+use {
+    ketupa_demo_engine::{tessellation_chain, vec2, Index, Tessellator, Vec2},
+    lyon_tessellation::{
+        path::Path, BuffersBuilder, FillOptions, FillTessellator, FillVertex, LineCap, LineJoin,
+        StrokeOptions, StrokeTessellator, StrokeVertex, VertexBuffers,
+    },
+    std::{
+        alloc::{GlobalAlloc, Layout, System},
+        sync::atomic::{AtomicUsize, Ordering},
+        time::{Duration, Instant},
+    },
+};
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+const FRAMES: usize = 200;
+const PATHS: usize = 48;
+
+fn main() {
+    let frames = (0..FRAMES).map(frame_paths).collect::<Vec<_>>();
+    report("fresh lyon tessellators", &frames, fresh());
+    report("persistent Tessellator", &frames, persistent());
+}
+
+fn frame_paths(frame: usize) -> Vec<Path> {
+    (0..PATHS)
+        .map(|i| {
+            let t = frame as f32 * 0.05 + i as f32;
+            let center = vec2(t.cos(), t.sin()) * 200.0;
+            let mut chain = tessellation_chain(16);
+            if i % 2 == 0 {
+                chain.star(center, 60.0 + t.sin() * 20.0, 25.0, 5 + i % 4);
+            } else {
+                chain.rounded_rect(
+                    center - 40.0,
+                    center + vec2(40.0, 30.0),
+                    8.0 + t.cos() * 4.0,
+                );
+            }
+            chain.finish()
+        })
+        .collect()
+}
+
+fn fresh() -> impl FnMut(&[Path]) {
+    let mut buffers = VertexBuffers::<Vec2, Index>::new();
+    move |paths| {
+        buffers.vertices.clear();
+        buffers.indices.clear();
+        for path in paths {
+            let mut builder =
+                BuffersBuilder::new(&mut buffers, |v: FillVertex<'_>| to_vec2(v.position()));
+            FillTessellator::new()
+                .tessellate(path, &fill_options(), &mut builder)
+                .unwrap();
+            let mut builder = BuffersBuilder::new(&mut buffers, |v: StrokeVertex<'_, '_>| {
+                to_vec2(v.position())
+            });
+            StrokeTessellator::new()
+                .tessellate(path, &stroke_options(), &mut builder)
+                .unwrap();
+        }
+    }
+}
+
+fn persistent() -> impl FnMut(&[Path]) {
+    let mut tessellator = Tessellator::new();
+    move |paths| {
+        tessellator.clear();
+        for path in paths {
+            tessellator.fill(path).unwrap();
+            tessellator.stroke(path, 4.0).unwrap();
+        }
+    }
+}
+
+fn report(name: &str, frames: &[Vec<Path>], mut tessellate: impl FnMut(&[Path])) {
+    tessellate(&frames[0]);
+    let mut elapsed = Duration::ZERO;
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    for paths in frames {
+        let start = Instant::now();
+        tessellate(paths);
+        elapsed += start.elapsed();
+    }
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    println!(
+        "{:<24} {:>8.1} allocations/frame {:>10.1?}/frame",
+        name,
+        allocations as f32 / frames.len() as f32,
+        elapsed / frames.len() as u32,
+    );
+}
+
+fn fill_options() -> FillOptions {
+    FillOptions::default().with_tolerance(0.0001)
+}
+
+fn stroke_options() -> StrokeOptions {
+    StrokeOptions::default()
+        .with_tolerance(0.0001)
+        .with_line_join(LineJoin::Bevel)
+        .with_line_cap(LineCap::Butt)
+        .with_line_width(4.0)
+}
+
+fn to_vec2(p: lyon_tessellation::math::Point) -> Vec2 {
+    vec2(p.x, p.y)
+}
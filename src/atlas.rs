@@ -0,0 +1,212 @@
+use {
+    crate::snapshot::read_png,
+    bytemuck::{Pod, Zeroable},
+    glam::{vec2, Vec2},
+    std::{io, path::Path},
+};
+
+const WHITE: u32 = 2;
+const PADDING: u32 = 1;
+
+#[repr(C)]
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub struct Region {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+unsafe impl Pod for Region {}
+unsafe impl Zeroable for Region {}
+
+impl Region {
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    pub fn uv(&self, uv: Vec2) -> Vec2 {
+        vec2(self.x + uv.x * self.width, self.y + uv.y * self.height)
+    }
+}
+
+pub struct Atlas {
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) pixels: Vec<u8>,
+    pub(crate) dirty: Option<[u32; 4]>,
+    size: (u32, u32),
+    cursor_x: u32,
+    cursor_y: u32,
+    shelf_height: u32,
+}
+
+impl Default for Atlas {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_SIZE, Self::DEFAULT_SIZE)
+    }
+}
+
+impl Atlas {
+    pub const DEFAULT_SIZE: u32 = 1024;
+
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            size: (width.max(WHITE), height.max(WHITE)),
+            ..Self::with_pixels(WHITE, WHITE)
+        }
+    }
+
+    fn with_pixels(width: u32, height: u32) -> Self {
+        let mut pixels = vec![0; (width * height * 4) as _];
+        for y in 0..WHITE {
+            let row = (y * width * 4) as usize;
+            pixels[row..row + (WHITE * 4) as usize].fill(255);
+        }
+        Self {
+            width,
+            height,
+            pixels,
+            dirty: Some([0, 0, width, height]),
+            size: (width, height),
+            cursor_x: WHITE,
+            cursor_y: 0,
+            shelf_height: WHITE,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn pixel_size(&self, region: Region) -> Vec2 {
+        vec2(
+            region.width * self.width as f32,
+            region.height * self.height as f32,
+        )
+    }
+
+    pub fn insert(&mut self, width: u32, height: u32, rgba: &[u8]) -> Option<Region> {
+        if width == 0 || height == 0 || rgba.len() < (width * height * 4) as usize {
+            return None;
+        }
+        if (self.width, self.height) != self.size {
+            *self = Self::with_pixels(self.size.0, self.size.1);
+        }
+        let (x, y) = self.allocate(width + PADDING * 2, height + PADDING * 2)?;
+        let (x, y) = (x + PADDING, y + PADDING);
+        for row in 0..height + PADDING * 2 {
+            let src_y = (row.max(PADDING) - PADDING).min(height - 1);
+            let dest_y = y + row - PADDING;
+            for column in 0..width + PADDING * 2 {
+                let src_x = (column.max(PADDING) - PADDING).min(width - 1);
+                let dest_x = x + column - PADDING;
+                let src = ((src_y * width + src_x) * 4) as usize;
+                let dest = ((dest_y * self.width + dest_x) * 4) as usize;
+                self.pixels[dest..dest + 4].copy_from_slice(&rgba[src..src + 4]);
+            }
+        }
+        self.mark([
+            x - PADDING,
+            y - PADDING,
+            x + width + PADDING,
+            y + height + PADDING,
+        ]);
+        Some(Region::new(
+            x as f32 / self.width as f32,
+            y as f32 / self.height as f32,
+            width as f32 / self.width as f32,
+            height as f32 / self.height as f32,
+        ))
+    }
+
+    pub fn load_png(&mut self, path: impl AsRef<Path>) -> io::Result<Region> {
+        let (width, height, rgba) = read_png(path)?;
+        self.insert(width, height, &rgba)
+            .ok_or_else(|| io::Error::other("texture atlas is full"))
+    }
+
+    pub(crate) fn clean(&mut self) {
+        self.dirty = None;
+    }
+
+    pub(crate) fn texel(&self, x: u32, y: u32) -> [u8; 4] {
+        let at = ((y * self.width + x) * 4) as usize;
+        [
+            self.pixels[at],
+            self.pixels[at + 1],
+            self.pixels[at + 2],
+            self.pixels[at + 3],
+        ]
+    }
+
+    fn allocate(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+        if width > self.width {
+            return None;
+        }
+        let (mut x, mut y, mut shelf_height) = (self.cursor_x, self.cursor_y, self.shelf_height);
+        if x + width > self.width {
+            x = 0;
+            y += shelf_height;
+            shelf_height = 0;
+        }
+        if y + height > self.height {
+            return None;
+        }
+        self.cursor_x = x + width;
+        self.cursor_y = y;
+        self.shelf_height = shelf_height.max(height);
+        Some((x, y))
+    }
+
+    fn mark(&mut self, [x0, y0, x1, y1]: [u32; 4]) {
+        self.dirty = Some(match self.dirty {
+            Some([a0, b0, a1, b1]) => [a0.min(x0), b0.min(y0), a1.max(x1), b1.max(y1)],
+            None => [x0, y0, x1, y1],
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allocate_on_shelves() {
+        let mut atlas = Atlas::with_pixels(16, 16);
+        assert_eq!(atlas.allocate(10, 4), Some((WHITE, 0)));
+        assert_eq!(atlas.allocate(8, 6), Some((0, 4)));
+        assert_eq!(atlas.allocate(8, 2), Some((8, 4)));
+        assert_eq!(atlas.allocate(17, 1), None);
+    }
+
+    #[test]
+    fn allocate_keeps_the_shelf_when_full() {
+        let mut atlas = Atlas::with_pixels(16, 8);
+        assert_eq!(atlas.allocate(12, 6), Some((WHITE, 0)));
+        assert_eq!(atlas.allocate(4, 4), None);
+        assert_eq!(atlas.allocate(2, 2), Some((14, 0)));
+    }
+
+    #[test]
+    fn allocates_pixels_on_first_insert() {
+        let mut atlas = Atlas::new(64, 32);
+        assert_eq!((atlas.width(), atlas.height()), (WHITE, WHITE));
+        assert_eq!(atlas.texel(0, 0), [255; 4]);
+        let region = atlas.insert(1, 1, &[1, 2, 3, 4]).unwrap();
+        assert_eq!((atlas.width(), atlas.height()), (64, 32));
+        assert_eq!(atlas.pixel_size(region), vec2(1.0, 1.0));
+        assert_eq!(atlas.texel(0, 0), [255; 4]);
+        assert_eq!(atlas.texel(WHITE + PADDING, PADDING), [1, 2, 3, 4]);
+        assert_eq!(atlas.dirty, Some([0, 0, 64, 32]));
+    }
+}
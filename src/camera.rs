@@ -0,0 +1,114 @@
+use glam::{Affine2, Mat2, Vec2};
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Camera {
+    pub translation: Vec2,
+    pub rotation: f32,
+    pub zoom: f32,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self::new(Vec2::ZERO, 0.0, 1.0)
+    }
+}
+
+impl Camera {
+    pub fn new(translation: Vec2, rotation: f32, zoom: f32) -> Self {
+        Self {
+            translation,
+            rotation,
+            zoom,
+        }
+    }
+
+    pub fn with_translation(self, translation: Vec2) -> Self {
+        Self {
+            translation,
+            ..self
+        }
+    }
+
+    pub fn with_rotation(self, rotation: f32) -> Self {
+        Self { rotation, ..self }
+    }
+
+    pub fn with_zoom(self, zoom: f32) -> Self {
+        Self { zoom, ..self }
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.zoom > 0.0 && self.zoom.is_finite()
+    }
+
+    pub fn view(&self) -> Affine2 {
+        Affine2::from_mat2(Mat2::from_angle(-self.rotation) * self.zoom.recip())
+            * Affine2::from_translation(-self.translation)
+    }
+
+    pub fn world_of(&self, local: Vec2) -> Vec2 {
+        self.translation + Mat2::from_angle(self.rotation) * (local * self.zoom)
+    }
+
+    pub fn local_of(&self, world: Vec2) -> Vec2 {
+        self.view().transform_point2(world)
+    }
+
+    pub fn pan(&mut self, local_delta: Vec2) {
+        self.translation -= Mat2::from_angle(self.rotation) * (local_delta * self.zoom);
+    }
+
+    pub fn zoom_about(&mut self, zoom: f32, local: Vec2) {
+        let focus = self.world_of(local);
+        self.zoom = zoom;
+        self.translation += focus - self.world_of(local);
+    }
+
+    pub fn rotate_about(&mut self, rotation: f32, local: Vec2) {
+        let focus = self.world_of(local);
+        self.rotation = rotation;
+        self.translation += focus - self.world_of(local);
+    }
+}
+
+pub fn camera(translation: Vec2, rotation: f32, zoom: f32) -> Camera {
+    Camera::new(translation, rotation, zoom)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, glam::vec2};
+
+    fn assert_close(a: Vec2, b: Vec2) {
+        assert!(a.abs_diff_eq(b, 1e-3), "{} != {}", a, b);
+    }
+
+    #[test]
+    fn world_and_local_round_trip() {
+        let camera = camera(vec2(120.0, -40.0), 0.7, 2.5);
+        for point in [Vec2::ZERO, vec2(10.0, 20.0), vec2(-300.0, 45.5)] {
+            assert_close(camera.local_of(camera.world_of(point)), point);
+            assert_close(camera.world_of(camera.local_of(point)), point);
+        }
+    }
+
+    #[test]
+    fn zoom_and_rotate_about_keep_the_focus() {
+        let mut camera = camera(vec2(5.0, 5.0), 0.3, 1.0);
+        let local = vec2(200.0, -100.0);
+        let focus = camera.world_of(local);
+        camera.zoom_about(4.0, local);
+        assert_close(camera.world_of(local), focus);
+        camera.rotate_about(-1.2, local);
+        assert_close(camera.world_of(local), focus);
+    }
+
+    #[test]
+    fn pan_moves_the_world_with_the_cursor() {
+        let mut camera = camera(Vec2::ZERO, 0.5, 2.0);
+        let world = vec2(30.0, 60.0);
+        let local = camera.local_of(world);
+        camera.pan(vec2(15.0, -8.0));
+        assert_close(camera.local_of(world), local + vec2(15.0, -8.0));
+    }
+}
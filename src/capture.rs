@@ -0,0 +1,237 @@
+use {
+    crate::{screen::Screen, snapshot::write_png},
+    std::{
+        ffi::OsString,
+        fs::{self, File},
+        io::{self, BufReader, BufWriter, Read, Write},
+        path::{Path, PathBuf},
+    },
+};
+
+enum Encoder {
+    Apng(PathBuf, BufWriter<File>),
+    Gif(gif::Encoder<BufWriter<File>>),
+}
+
+struct Recording {
+    path: PathBuf,
+    fps: u32,
+    width: u32,
+    height: u32,
+    frames: u32,
+    encoder: Encoder,
+}
+
+impl Recording {
+    fn new(path: &Path, fps: u32, width: u32, height: u32) -> io::Result<Self> {
+        let extension = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_ascii_lowercase());
+        let encoder = match extension.as_deref() {
+            Some("png" | "apng") => {
+                let mut spool = OsString::from(path);
+                spool.push(".frames");
+                let spool = PathBuf::from(spool);
+                let file = File::create(&spool)?;
+                Encoder::Apng(spool, BufWriter::new(file))
+            }
+            Some("gif") if width > u16::MAX as u32 || height > u16::MAX as u32 => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("cannot record {}x{} frames into a GIF", width, height),
+                ))
+            }
+            Some("gif") => {
+                let mut encoder = gif::Encoder::new(
+                    BufWriter::new(File::create(path)?),
+                    width as _,
+                    height as _,
+                    &[],
+                )
+                .map_err(io::Error::other)?;
+                encoder
+                    .set_repeat(gif::Repeat::Infinite)
+                    .map_err(io::Error::other)?;
+                Encoder::Gif(encoder)
+            }
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("cannot record into {}, use .png or .gif", path.display()),
+                ))
+            }
+        };
+        Ok(Self {
+            path: path.to_owned(),
+            fps,
+            width,
+            height,
+            frames: 0,
+            encoder,
+        })
+    }
+
+    fn push(&mut self, width: u32, height: u32, rgba: Vec<u8>) -> io::Result<()> {
+        let mut rgba = fit(rgba, width, height, self.width, self.height);
+        match &mut self.encoder {
+            Encoder::Apng(_, spool) => spool.write_all(&rgba)?,
+            Encoder::Gif(encoder) => {
+                let mut frame =
+                    gif::Frame::from_rgba_speed(self.width as _, self.height as _, &mut rgba, 10);
+                let centis =
+                    |frames: u32| (frames as u64 * 100 + self.fps as u64 / 2) / self.fps as u64;
+                frame.delay = (centis(self.frames + 1) - centis(self.frames)) as _;
+                encoder.write_frame(&frame).map_err(io::Error::other)?;
+            }
+        }
+        self.frames += 1;
+        Ok(())
+    }
+
+    fn finish(self) {
+        let path = self.path.clone();
+        if let Err(error) = self.encode() {
+            log::error!("failed to record {}: {}", path.display(), error);
+        }
+    }
+
+    fn encode(self) -> io::Result<()> {
+        match self.encoder {
+            Encoder::Apng(spool, writer) => {
+                drop(writer.into_inner().map_err(|error| error.into_error())?);
+                let encoded = encode_apng(&self.path, &spool, self.fps, self.width, self.height);
+                fs::remove_file(&spool)?;
+                encoded
+            }
+            Encoder::Gif(_) => Ok(()),
+        }
+    }
+}
+
+fn encode_apng(path: &Path, spool: &Path, fps: u32, width: u32, height: u32) -> io::Result<()> {
+    let mut frame = vec![0; (width * height * 4) as _];
+    let frames = fs::metadata(spool)?.len() / frame.len() as u64;
+    let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_animated(frames.max(1) as _, 0)?;
+    encoder.set_frame_delay(1, fps.min(u16::MAX as _) as _)?;
+    let mut writer = encoder.write_header()?;
+    let mut spool = BufReader::new(File::open(spool)?);
+    for _ in 0..frames.max(1) {
+        if frames > 0 {
+            spool.read_exact(&mut frame)?;
+        }
+        writer.write_image_data(&frame)?;
+    }
+    writer.finish()?;
+    Ok(())
+}
+
+fn fit(rgba: Vec<u8>, width: u32, height: u32, to_width: u32, to_height: u32) -> Vec<u8> {
+    if (width, height) == (to_width, to_height) {
+        return rgba;
+    }
+    let mut fitted = vec![0; (to_width * to_height * 4) as _];
+    let row = (width.min(to_width) * 4) as usize;
+    for y in 0..height.min(to_height) as usize {
+        let src = y * width as usize * 4;
+        let dst = y * to_width as usize * 4;
+        fitted[dst..dst + row].copy_from_slice(&rgba[src..src + row]);
+    }
+    fitted
+}
+
+pub(crate) struct Capture {
+    recording: Option<Recording>,
+}
+
+impl Capture {
+    pub fn new() -> Self {
+        Self { recording: None }
+    }
+
+    pub fn process(&mut self, screen: &mut Screen, width: u32, height: u32, rgba: Option<Vec<u8>>) {
+        if let Some(rgba) = &rgba {
+            if let Some(path) = screen.take_capture() {
+                if let Err(error) = write_png(&path, width, height, rgba) {
+                    log::error!("failed to capture {}: {}", path.display(), error);
+                }
+            }
+        }
+        let is_same = matches!(
+            (&self.recording, screen.recording()),
+            (Some(recording), Some((path, fps))) if recording.path == *path && recording.fps == *fps
+        );
+        if !is_same {
+            if let Some(recording) = self.recording.take() {
+                recording.finish();
+            }
+            if let Some((path, fps)) = screen.recording() {
+                match Recording::new(path, *fps, width, height) {
+                    Ok(recording) => self.recording = Some(recording),
+                    Err(error) => {
+                        log::error!("failed to record {}: {}", path.display(), error);
+                        screen.stop_recording();
+                    }
+                }
+            }
+        }
+        if let (Some(recording), Some(rgba)) = (&mut self.recording, rgba) {
+            if let Err(error) = recording.push(width, height, rgba) {
+                log::error!("failed to record {}: {}", recording.path.display(), error);
+                if let Some(recording) = self.recording.take() {
+                    recording.finish();
+                }
+                screen.stop_recording();
+            }
+        }
+    }
+
+    pub fn finish(&mut self) {
+        if let Some(recording) = self.recording.take() {
+            recording.finish();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn streams_apng_frames() {
+        let path = std::env::temp_dir().join("ketupa_streams_apng_frames.png");
+        let mut recording = Recording::new(&path, 30, 2, 2).unwrap();
+        for value in [0, 128, 255] {
+            recording.push(2, 2, vec![value; 16]).unwrap();
+        }
+        let spool = match &recording.encoder {
+            Encoder::Apng(spool, _) => spool.clone(),
+            Encoder::Gif(_) => unreachable!(),
+        };
+        recording.finish();
+        assert!(!spool.exists());
+
+        let mut reader = png::Decoder::new(File::open(&path).unwrap())
+            .read_info()
+            .unwrap();
+        assert_eq!(reader.info().animation_control.unwrap().num_frames, 3);
+        let mut frame = vec![0; reader.output_buffer_size()];
+        for value in [0, 128, 255] {
+            reader.next_frame(&mut frame).unwrap();
+            assert_eq!(frame, [value; 16]);
+        }
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn rejects_oversized_gif() {
+        let path = std::env::temp_dir().join("ketupa_rejects_oversized_gif.gif");
+        let error = Recording::new(&path, 30, u16::MAX as u32 + 1, 2)
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,164 @@
+use {
+    crate::layers::{color, Color},
+    glam::Vec2,
+};
+
+const MAX_COLOR_ERROR: u8 = 1;
+const OFFSET_EPSILON: f32 = 0.001;
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Gradient {
+    Linear {
+        start: Vec2,
+        end: Vec2,
+        stops: Vec<(f32, Color)>,
+    },
+    Radial {
+        center: Vec2,
+        radius: f32,
+        stops: Vec<(f32, Color)>,
+    },
+}
+
+impl Gradient {
+    pub fn linear(start: Vec2, end: Vec2, stops: impl Into<Vec<(f32, Color)>>) -> Self {
+        Self::Linear {
+            start,
+            end,
+            stops: sorted(stops.into()),
+        }
+    }
+
+    pub fn radial(center: Vec2, radius: f32, stops: impl Into<Vec<(f32, Color)>>) -> Self {
+        Self::Radial {
+            center,
+            radius,
+            stops: sorted(stops.into()),
+        }
+    }
+
+    pub fn offset(&self, position: Vec2) -> f32 {
+        match self {
+            Self::Linear { start, end, .. } => {
+                let axis = *end - *start;
+                let len = axis.length_squared();
+                if len > 0.0 {
+                    (position - *start).dot(axis) / len
+                } else {
+                    0.0
+                }
+            }
+            Self::Radial { center, radius, .. } => {
+                if *radius > 0.0 {
+                    position.distance(*center) / radius
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+
+    pub fn color_at(&self, position: Vec2) -> Color {
+        let stops = self.stops();
+        let t = self.offset(position);
+        let next = stops.iter().position(|&(offset, _)| offset > t);
+        match next {
+            None => stops.last().map_or(Color::WHITE, |&(_, color)| color),
+            Some(0) => stops[0].1,
+            Some(i) => {
+                let (from, a) = stops[i - 1];
+                let (to, b) = stops[i];
+                mix(a, b, (t - from) / (to - from))
+            }
+        }
+    }
+
+    pub(crate) fn split(&self, a: Vec2, b: Vec2) -> Option<Vec2> {
+        let (from, to) = (self.offset(a), self.offset(b));
+        if let Self::Radial { center, .. } = self {
+            let axis = b - a;
+            let closest = (*center - a).dot(axis) / axis.length_squared();
+            if closest > OFFSET_EPSILON
+                && closest < 1.0 - OFFSET_EPSILON
+                && self.offset(a.lerp(b, closest)) < from.min(to) - OFFSET_EPSILON
+            {
+                return Some(a.lerp(b, closest));
+            }
+        }
+        let (low, high) = (from.min(to), from.max(to));
+        let middle = (from + to) * 0.5;
+        let stop = self
+            .stops()
+            .iter()
+            .map(|&(offset, _)| offset)
+            .filter(|&offset| offset > low + OFFSET_EPSILON && offset < high - OFFSET_EPSILON)
+            .min_by(|x, y| (x - middle).abs().total_cmp(&(y - middle).abs()));
+        if let Some(stop) = stop {
+            return Some(a.lerp(b, self.fraction(a, b, stop)));
+        }
+        let mid = (a + b) * 0.5;
+        let expected = mix(self.color_at(a), self.color_at(b), 0.5);
+        (!self.is_close(mid, expected)).then_some(mid)
+    }
+
+    pub(crate) fn is_linear(&self, [a, b, c]: [Vec2; 3]) -> bool {
+        let [a_color, b_color, c_color] = [a, b, c].map(|corner| self.color_at(corner));
+        let expected = mix(mix(a_color, b_color, 0.5), c_color, 1.0 / 3.0);
+        self.is_close((a + b + c) / 3.0, expected)
+    }
+
+    fn is_close(&self, position: Vec2, expected: Color) -> bool {
+        let actual = self.color_at(position);
+        let error = [
+            expected.r.abs_diff(actual.r),
+            expected.g.abs_diff(actual.g),
+            expected.b.abs_diff(actual.b),
+            expected.a.abs_diff(actual.a),
+        ];
+        error.into_iter().all(|error| error <= MAX_COLOR_ERROR)
+    }
+
+    fn fraction(&self, a: Vec2, b: Vec2, offset: f32) -> f32 {
+        let fraction = match self {
+            Self::Linear { .. } => {
+                let (from, to) = (self.offset(a), self.offset(b));
+                (offset - from) / (to - from)
+            }
+            Self::Radial { center, radius, .. } => {
+                let (start, axis) = (a - *center, b - a);
+                let qa = axis.length_squared();
+                let qb = 2.0 * start.dot(axis);
+                let qc = start.length_squared() - (offset * radius).powi(2);
+                let root = (qb * qb - 4.0 * qa * qc).max(0.0).sqrt();
+                let near = (-qb - root) / (2.0 * qa);
+                if (0.0..=1.0).contains(&near) {
+                    near
+                } else {
+                    (-qb + root) / (2.0 * qa)
+                }
+            }
+        };
+        fraction.clamp(0.0, 1.0)
+    }
+
+    fn stops(&self) -> &[(f32, Color)] {
+        match self {
+            Self::Linear { stops, .. } | Self::Radial { stops, .. } => stops,
+        }
+    }
+}
+
+fn sorted(mut stops: Vec<(f32, Color)>) -> Vec<(f32, Color)> {
+    stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+    stops
+}
+
+fn mix(a: Color, b: Color, t: f32) -> Color {
+    let channel = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+    color(
+        channel(a.r, b.r),
+        channel(a.g, b.g),
+        channel(a.b, b.b),
+        channel(a.a, b.a),
+    )
+}
@@ -1,255 +1,1127 @@
 use {
-    crate::layers::{Color, Instance, Layers},
-    bytemuck::cast_slice,
-    glam::{vec2, Vec2},
-    std::{iter::once, mem::size_of},
+    crate::{
+        atlas::Atlas,
+        camera::Camera,
+        layers::{Attributes, BlendMode, Color, Draw, Index, Instance, Layers},
+        raster::Raster,
+    },
+    bytemuck::{cast_slice, Pod},
+    glam::{vec2, Affine2, Vec2},
+    pollster::FutureExt as _,
+    softbuffer::GraphicsContext,
+    std::{
+        iter::once,
+        mem::size_of,
+        num::{NonZeroU32, NonZeroU64},
+        ops::Range,
+        rc::Rc,
+    },
     wgpu::*,
     winit::{dpi::PhysicalSize, window::Window},
 };
 
+#[cfg(not(feature = "u32_indices"))]
+const INDEX_FORMAT: IndexFormat = IndexFormat::Uint16;
+
+#[cfg(feature = "u32_indices")]
+const INDEX_FORMAT: IndexFormat = IndexFormat::Uint32;
+
+const MSAA_SAMPLES: u32 = 4;
+
 #[repr(C)]
 #[derive(Copy, Clone)]
 pub(crate) struct Params {
+    pub view: [f32; 6],
     pub screen_half_recip: Vec2,
-    pub screen_zoom: f32,
-    dummy: f32,
+    pub opacity: f32,
+    pub premultiply: f32,
 }
 
 impl Params {
-    pub fn new(screen_width: u32, screen_height: u32, screen_zoom: f32) -> Self {
+    pub fn new(
+        screen_width: u32,
+        screen_height: u32,
+        view: Affine2,
+        opacity: f32,
+        blend: BlendMode,
+    ) -> Self {
         let half = vec2(screen_width as _, screen_height as _) * 0.5;
         Self {
+            view: view.to_cols_array(),
             screen_half_recip: half.recip(),
-            screen_zoom,
-            dummy: 0.0,
+            opacity,
+            premultiply: if blend == BlendMode::Premultiplied {
+                0.0
+            } else {
+                1.0
+            },
         }
     }
 
-    pub fn into_array(self) -> [f32; 4] {
+    pub fn into_array(self) -> [f32; 10] {
+        let [a, b, c, d, x, y] = self.view;
         [
+            a,
+            b,
+            c,
+            d,
+            x,
+            y,
             self.screen_half_recip.x,
             self.screen_half_recip.y,
-            self.screen_zoom,
-            self.dummy,
+            self.opacity,
+            self.premultiply,
         ]
     }
 }
 
-pub(crate) struct Graphics {
-    pub(crate) layers: Layers,
-    surface: Surface,
-    device: Device,
-    queue: Queue,
-    config: SurfaceConfiguration,
-    vertex_buffer: Buffer,
-    index_buffer: Buffer,
-    instance_buffer: Buffer,
-    params_buffer: Buffer,
-    params_bind_group: BindGroup,
-    pipeline: RenderPipeline,
+fn blend_state(mode: BlendMode) -> BlendState {
+    let color = |src_factor, dst_factor| BlendComponent {
+        src_factor,
+        dst_factor,
+        operation: BlendOperation::Add,
+    };
+    BlendState {
+        color: match mode {
+            BlendMode::Alpha | BlendMode::Premultiplied => {
+                color(BlendFactor::One, BlendFactor::OneMinusSrcAlpha)
+            }
+            BlendMode::Additive => color(BlendFactor::One, BlendFactor::One),
+            BlendMode::Multiply => color(BlendFactor::Dst, BlendFactor::OneMinusSrcAlpha),
+            BlendMode::Screen => color(BlendFactor::One, BlendFactor::OneMinusSrc),
+        },
+        alpha: BlendComponent::OVER,
+    }
+}
+
+pub(crate) fn sample_count(samples: u32) -> u32 {
+    if samples > 1 {
+        MSAA_SAMPLES
+    } else {
+        1
+    }
+}
+
+fn adapter_sample_count(adapter: &Adapter, format: TextureFormat, samples: u32) -> u32 {
+    let samples = sample_count(samples);
+    // wgpu 0.12 does not report which counts a format supports, 4 is guaranteed for render targets.
+    let renderable = adapter
+        .get_texture_format_features(format)
+        .allowed_usages
+        .contains(TextureUsages::RENDER_ATTACHMENT);
+    if samples > 1 && !renderable {
+        log::warn!("{:?} is not renderable, drawing without MSAA", format);
+        return 1;
+    }
+    samples
+}
+
+pub(crate) async fn request_adapter(
+    instance: &wgpu::Instance,
+    compatible_surface: Option<&Surface>,
+) -> Option<Adapter> {
+    for force_fallback_adapter in [false, true] {
+        let adapter = instance
+            .request_adapter(&RequestAdapterOptions {
+                compatible_surface,
+                force_fallback_adapter,
+                ..Default::default()
+            })
+            .await;
+        if adapter.is_some() {
+            return adapter;
+        }
+    }
+    None
 }
 
 fn create_buffer<T>(device: &Device, len: usize, usage: BufferUsages) -> Buffer {
     device.create_buffer(&BufferDescriptor {
         label: None,
-        size: (len * size_of::<T>()) as _,
+        size: (len * size_of::<T>()).div_ceil(COPY_BUFFER_ALIGNMENT as _) as u64
+            * COPY_BUFFER_ALIGNMENT,
         usage: usage | BufferUsages::COPY_DST,
         mapped_at_creation: false,
     })
 }
 
-impl Graphics {
-    pub async fn new(window: &Window, layers: Layers) -> Self {
-        let instance = wgpu::Instance::new(Backends::all());
-        let surface = unsafe { instance.create_surface(window) };
-        let adapter = instance
-            .request_adapter(&RequestAdapterOptions {
-                compatible_surface: Some(&surface),
-                ..Default::default()
-            })
-            .await
-            .unwrap();
+fn create_params(
+    device: &Device,
+    layout: &BindGroupLayout,
+    stride: usize,
+    len: usize,
+) -> (Buffer, BindGroup) {
+    let buffer = create_buffer::<u8>(device, stride * len.max(1), BufferUsages::UNIFORM);
+    let bind_group = device.create_bind_group(&BindGroupDescriptor {
+        layout,
+        entries: &[BindGroupEntry {
+            binding: 0,
+            resource: BindingResource::Buffer(BufferBinding {
+                buffer: &buffer,
+                offset: 0,
+                size: NonZeroU64::new(size_of::<Params>() as _),
+            }),
+        }],
+        label: None,
+    });
+    (buffer, bind_group)
+}
+
+fn write_slab<T: Pod>(queue: &Queue, buffer: &Buffer, slab: &[T], range: Range<usize>) {
+    if range.start >= range.end {
+        return;
+    }
+    let bytes: &[u8] = cast_slice(slab);
+    let align = COPY_BUFFER_ALIGNMENT as usize;
+    let start = range.start * size_of::<T>() / align * align;
+    let end = (range.end * size_of::<T>()).div_ceil(align) * align;
+    if end <= bytes.len() {
+        queue.write_buffer(buffer, start as _, &bytes[start..end]);
+    } else {
+        let mut padded = bytes[start..].to_vec();
+        padded.resize(end - start, 0);
+        queue.write_buffer(buffer, start as _, &padded);
+    }
+}
+
+fn create_atlas(
+    device: &Device,
+    layout: &BindGroupLayout,
+    sampler: &Sampler,
+    atlas: &Atlas,
+) -> (Texture, BindGroup) {
+    let texture = device.create_texture(&TextureDescriptor {
+        label: None,
+        size: Extent3d {
+            width: atlas.width,
+            height: atlas.height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format: TextureFormat::Rgba8UnormSrgb,
+        usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+    });
+    let view = texture.create_view(&Default::default());
+    let bind_group = device.create_bind_group(&BindGroupDescriptor {
+        layout,
+        entries: &[
+            BindGroupEntry {
+                binding: 0,
+                resource: BindingResource::TextureView(&view),
+            },
+            BindGroupEntry {
+                binding: 1,
+                resource: BindingResource::Sampler(sampler),
+            },
+        ],
+        label: None,
+    });
+    (texture, bind_group)
+}
 
+pub(crate) struct Renderer {
+    pub(crate) device: Device,
+    pub(crate) queue: Queue,
+    vertex_buffer: Buffer,
+    uv_buffer: Buffer,
+    attribute_buffer: Option<Buffer>,
+    color_buffer: Option<Buffer>,
+    index_buffer: Buffer,
+    instance_buffer: Buffer,
+    params_buffer: Buffer,
+    params_bind_group_layout: BindGroupLayout,
+    params_bind_group: BindGroup,
+    params_stride: usize,
+    params_capacity: usize,
+    params: Vec<u8>,
+    draws: Vec<Draw>,
+    atlas_bind_group_layout: BindGroupLayout,
+    atlas_sampler: Sampler,
+    atlas_texture: Texture,
+    atlas_bind_group: BindGroup,
+    atlas_size: (u32, u32),
+    pipelines: [RenderPipeline; BlendMode::ALL.len()],
+    samples: u32,
+    multisample: Option<Multisample>,
+    vertices_capacity: usize,
+    indices_capacity: usize,
+    instances_capacity: usize,
+    is_uploaded: bool,
+}
+
+impl Renderer {
+    pub async fn new(
+        adapter: &Adapter,
+        format: TextureFormat,
+        layers: &Layers,
+        samples: u32,
+    ) -> Option<Self> {
+        let samples = adapter_sample_count(adapter, format, samples);
         let (device, queue) = adapter
             .request_device(&Default::default(), None)
             .await
-            .unwrap();
-
-        let size = window.inner_size();
-        let config = SurfaceConfiguration {
-            usage: TextureUsages::RENDER_ATTACHMENT,
-            format: surface.get_preferred_format(&adapter).unwrap(),
-            width: size.width,
-            height: size.height,
-            present_mode: PresentMode::Fifo,
-        };
-        surface.configure(&device, &config);
+            .ok()?;
 
         let shader = device.create_shader_module(&include_wgsl!("shader.wgsl"));
 
         let vertex_buffer =
             create_buffer::<Vec2>(&device, layers.vertices.len(), BufferUsages::VERTEX);
-        let index_buffer = create_buffer::<u16>(&device, layers.indices.len(), BufferUsages::INDEX);
+        let uv_buffer = create_buffer::<Vec2>(&device, layers.uvs.len(), BufferUsages::VERTEX);
+        let attribute_buffer = layers.has_attributes().then(|| {
+            create_buffer::<Attributes>(&device, layers.vertices.len(), BufferUsages::VERTEX)
+        });
+        let color_buffer = layers
+            .has_colors()
+            .then(|| create_buffer::<Color>(&device, layers.vertices.len(), BufferUsages::VERTEX));
+        let index_buffer =
+            create_buffer::<Index>(&device, layers.indices.len(), BufferUsages::INDEX);
         let instance_buffer =
             create_buffer::<Instance>(&device, layers.instances.len(), BufferUsages::VERTEX);
-        let params_buffer = create_buffer::<Params>(&device, 1, BufferUsages::UNIFORM);
 
         let params_bind_group_layout =
             device.create_bind_group_layout(&BindGroupLayoutDescriptor {
                 entries: &[BindGroupLayoutEntry {
                     binding: 0,
-                    visibility: ShaderStages::VERTEX,
+                    visibility: ShaderStages::VERTEX | ShaderStages::FRAGMENT,
                     ty: BindingType::Buffer {
                         ty: BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
+                        has_dynamic_offset: true,
+                        min_binding_size: NonZeroU64::new(size_of::<Params>() as _),
                     },
                     count: None,
                 }],
                 label: None,
             });
 
-        let params_bind_group = device.create_bind_group(&BindGroupDescriptor {
-            layout: &params_bind_group_layout,
-            entries: &[BindGroupEntry {
-                binding: 0,
-                resource: params_buffer.as_entire_binding(),
-            }],
+        let align = device.limits().min_uniform_buffer_offset_alignment as usize;
+        let params_stride = size_of::<Params>().div_ceil(align) * align;
+        let (params_buffer, params_bind_group) = create_params(
+            &device,
+            &params_bind_group_layout,
+            params_stride,
+            layers.ranges.len(),
+        );
+
+        let atlas_bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
             label: None,
         });
 
+        let atlas_sampler = device.create_sampler(&SamplerDescriptor {
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let (atlas_texture, atlas_bind_group) = create_atlas(
+            &device,
+            &atlas_bind_group_layout,
+            &atlas_sampler,
+            &layers.atlas,
+        );
+
         let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
-            bind_group_layouts: &[&params_bind_group_layout],
+            bind_group_layouts: &[&params_bind_group_layout, &atlas_bind_group_layout],
             ..Default::default()
         });
 
-        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
-            label: None,
-            layout: Some(&pipeline_layout),
-            vertex: VertexState {
-                module: &shader,
-                entry_point: "vs_main",
-                buffers: &[
-                    VertexBufferLayout {
-                        array_stride: size_of::<Vec2>() as _,
-                        step_mode: VertexStepMode::Vertex,
-                        attributes: &vertex_attr_array![0 => Float32x2],
-                    },
-                    VertexBufferLayout {
-                        array_stride: size_of::<Instance>() as _,
-                        step_mode: VertexStepMode::Instance,
-                        attributes: &vertex_attr_array![1 => Float32x4, 2 => Float32x3],
-                    },
-                ],
+        let position_attributes = vertex_attr_array![0 => Float32x2];
+        let instance_attributes = vertex_attr_array![
+            1 => Float32x4, 2 => Float32x3, 3 => Float32x4, 8 => Float32
+        ];
+        let uv_attributes = vertex_attr_array![4 => Float32x2];
+        let attribute_attributes = vertex_attr_array![5 => Float32x2];
+        let color_attributes = vertex_attr_array![9 => Unorm8x4];
+        let mut buffers = vec![
+            VertexBufferLayout {
+                array_stride: size_of::<Vec2>() as _,
+                step_mode: VertexStepMode::Vertex,
+                attributes: &position_attributes,
             },
-            fragment: Some(FragmentState {
-                module: &shader,
-                entry_point: "fs_main",
-                targets: &[ColorTargetState {
-                    format: config.format,
-                    blend: Some(BlendState::ALPHA_BLENDING),
-                    write_mask: ColorWrites::ALL,
-                }],
-            }),
-            primitive: PrimitiveState {
-                topology: PrimitiveTopology::TriangleList,
-                front_face: FrontFace::Cw,
-                ..Default::default()
+            VertexBufferLayout {
+                array_stride: size_of::<Instance>() as _,
+                step_mode: VertexStepMode::Instance,
+                attributes: &instance_attributes,
             },
-            depth_stencil: None,
-            multisample: Default::default(),
-            multiview: None,
+            VertexBufferLayout {
+                array_stride: size_of::<Vec2>() as _,
+                step_mode: VertexStepMode::Vertex,
+                attributes: &uv_attributes,
+            },
+        ];
+        if attribute_buffer.is_some() {
+            buffers.push(VertexBufferLayout {
+                array_stride: size_of::<Attributes>() as _,
+                step_mode: VertexStepMode::Vertex,
+                attributes: &attribute_attributes,
+            });
+        }
+        if color_buffer.is_some() {
+            buffers.push(VertexBufferLayout {
+                array_stride: size_of::<Color>() as _,
+                step_mode: VertexStepMode::Vertex,
+                attributes: &color_attributes,
+            });
+        }
+        let entry_point = match (attribute_buffer.is_some(), color_buffer.is_some()) {
+            (false, false) => "vs_main",
+            (true, false) => "vs_attributes",
+            (false, true) => "vs_colors",
+            (true, true) => "vs_attributes_colors",
+        };
+
+        let pipelines = BlendMode::ALL.map(|mode| {
+            device.create_render_pipeline(&RenderPipelineDescriptor {
+                label: None,
+                layout: Some(&pipeline_layout),
+                vertex: VertexState {
+                    module: &shader,
+                    entry_point,
+                    buffers: &buffers,
+                },
+                fragment: Some(FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &[ColorTargetState {
+                        format,
+                        blend: Some(blend_state(mode)),
+                        write_mask: ColorWrites::ALL,
+                    }],
+                }),
+                primitive: PrimitiveState {
+                    topology: PrimitiveTopology::TriangleList,
+                    front_face: FrontFace::Cw,
+                    ..Default::default()
+                },
+                depth_stencil: None,
+                multisample: MultisampleState {
+                    count: samples,
+                    ..Default::default()
+                },
+                multiview: None,
+            })
         });
 
-        Self {
-            layers,
-            surface,
+        Some(Self {
             device,
             queue,
-            config,
-            pipeline,
+            pipelines,
+            samples,
+            multisample: None,
             vertex_buffer,
+            uv_buffer,
+            attribute_buffer,
+            color_buffer,
             index_buffer,
             instance_buffer,
             params_buffer,
+            params_bind_group_layout,
             params_bind_group,
+            params_stride,
+            params_capacity: layers.ranges.len().max(1),
+            params: Vec::new(),
+            draws: Vec::new(),
+            atlas_size: (layers.atlas.width, layers.atlas.height),
+            atlas_bind_group_layout,
+            atlas_sampler,
+            atlas_texture,
+            atlas_bind_group,
+            vertices_capacity: layers.vertices.len(),
+            indices_capacity: layers.indices.len(),
+            instances_capacity: layers.instances.len(),
+            is_uploaded: false,
+        })
+    }
+
+    fn fit(&mut self, layers: &Layers) -> bool {
+        let mut recreated = false;
+        if layers.vertices.len() > self.vertices_capacity {
+            recreated = true;
+            self.vertices_capacity = layers.vertices.len();
+            self.vertex_buffer =
+                create_buffer::<Vec2>(&self.device, self.vertices_capacity, BufferUsages::VERTEX);
+            self.uv_buffer =
+                create_buffer::<Vec2>(&self.device, self.vertices_capacity, BufferUsages::VERTEX);
+            if let Some(buffer) = &mut self.attribute_buffer {
+                *buffer = create_buffer::<Attributes>(
+                    &self.device,
+                    self.vertices_capacity,
+                    BufferUsages::VERTEX,
+                );
+            }
+            if let Some(buffer) = &mut self.color_buffer {
+                *buffer = create_buffer::<Color>(
+                    &self.device,
+                    self.vertices_capacity,
+                    BufferUsages::VERTEX,
+                );
+            }
+        }
+        if layers.indices.len() > self.indices_capacity {
+            recreated = true;
+            self.indices_capacity = layers.indices.len();
+            self.index_buffer =
+                create_buffer::<Index>(&self.device, self.indices_capacity, BufferUsages::INDEX);
         }
+        if layers.instances.len() > self.instances_capacity {
+            recreated = true;
+            self.instances_capacity = layers.instances.len();
+            self.instance_buffer = create_buffer::<Instance>(
+                &self.device,
+                self.instances_capacity,
+                BufferUsages::VERTEX,
+            );
+        }
+        if layers.ranges.len() > self.params_capacity {
+            self.params_capacity = layers.ranges.len();
+            (self.params_buffer, self.params_bind_group) = create_params(
+                &self.device,
+                &self.params_bind_group_layout,
+                self.params_stride,
+                self.params_capacity,
+            );
+        }
+        if (layers.atlas.width, layers.atlas.height) != self.atlas_size {
+            recreated = true;
+            self.atlas_size = (layers.atlas.width, layers.atlas.height);
+            (self.atlas_texture, self.atlas_bind_group) = create_atlas(
+                &self.device,
+                &self.atlas_bind_group_layout,
+                &self.atlas_sampler,
+                &layers.atlas,
+            );
+        }
+        recreated
     }
 
-    pub fn render(
+    fn write_atlas(&self, atlas: &Atlas, [x0, y0, x1, y1]: [u32; 4]) {
+        if x0 >= x1 || y0 >= y1 {
+            return;
+        }
+        self.queue.write_texture(
+            ImageCopyTexture {
+                texture: &self.atlas_texture,
+                mip_level: 0,
+                origin: Origin3d { x: x0, y: y0, z: 0 },
+                aspect: TextureAspect::All,
+            },
+            &atlas.pixels,
+            ImageDataLayout {
+                offset: ((y0 * atlas.width + x0) * 4) as _,
+                bytes_per_row: NonZeroU32::new(atlas.width * 4),
+                rows_per_image: None,
+            },
+            Extent3d {
+                width: x1 - x0,
+                height: y1 - y0,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+
+    pub fn upload(&mut self, layers: &Layers) {
+        self.fit(layers);
+        self.is_uploaded = true;
+        write_slab(
+            &self.queue,
+            &self.vertex_buffer,
+            &layers.vertices,
+            0..layers.vertices.len(),
+        );
+        write_slab(
+            &self.queue,
+            &self.uv_buffer,
+            &layers.uvs,
+            0..layers.uvs.len(),
+        );
+        if let Some(buffer) = &self.attribute_buffer {
+            write_slab(
+                &self.queue,
+                buffer,
+                &layers.attributes,
+                0..layers.attributes.len(),
+            );
+        }
+        if let Some(buffer) = &self.color_buffer {
+            write_slab(&self.queue, buffer, &layers.colors, 0..layers.colors.len());
+        }
+        write_slab(
+            &self.queue,
+            &self.index_buffer,
+            &layers.indices,
+            0..layers.indices.len(),
+        );
+        write_slab(
+            &self.queue,
+            &self.instance_buffer,
+            &layers.instances,
+            0..layers.instances.len(),
+        );
+        self.write_atlas(
+            &layers.atlas,
+            [0, 0, layers.atlas.width, layers.atlas.height],
+        );
+    }
+
+    pub fn upload_dirty(&mut self, layers: &mut Layers) {
+        if self.fit(layers) || layers.relayout || !self.is_uploaded {
+            self.upload(layers);
+        } else {
+            for (dirty, channels) in layers.dirty.iter().zip(layers.channels.iter()) {
+                write_slab(
+                    &self.queue,
+                    &self.vertex_buffer,
+                    &layers.vertices,
+                    dirty.vertices.clone(),
+                );
+                write_slab(
+                    &self.queue,
+                    &self.uv_buffer,
+                    &layers.uvs,
+                    dirty.vertices.clone(),
+                );
+                if let Some(buffer) = self
+                    .attribute_buffer
+                    .as_ref()
+                    .filter(|_| channels.attributes)
+                {
+                    write_slab(
+                        &self.queue,
+                        buffer,
+                        &layers.attributes,
+                        dirty.vertices.clone(),
+                    );
+                }
+                if let Some(buffer) = self.color_buffer.as_ref().filter(|_| channels.colors) {
+                    write_slab(&self.queue, buffer, &layers.colors, dirty.vertices.clone());
+                }
+                write_slab(
+                    &self.queue,
+                    &self.index_buffer,
+                    &layers.indices,
+                    dirty.indices.clone(),
+                );
+                write_slab(
+                    &self.queue,
+                    &self.instance_buffer,
+                    &layers.instances,
+                    dirty.instances.clone(),
+                );
+            }
+            if let Some(dirty) = layers.atlas.dirty {
+                self.write_atlas(&layers.atlas, dirty);
+            }
+        }
+        layers.clean();
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw(
         &mut self,
-        size: PhysicalSize<u32>,
-        zoom: f32,
+        encoder: &mut CommandEncoder,
+        view: &TextureView,
+        format: TextureFormat,
+        layers: &Layers,
+        width: u32,
+        height: u32,
+        camera: &Camera,
         clear_color: Color,
-    ) -> Result<(), ()> {
-        if (size.width != self.config.width || size.height != self.config.height)
-            && size.width != 0
-            && size.height != 0
-        {
-            self.config.width = size.width;
-            self.config.height = size.height;
-            self.surface.configure(&self.device, &self.config);
+    ) {
+        self.params.clear();
+        for (view, composite) in layers.views.iter().zip(layers.composites.iter()) {
+            let view = view.view(camera).unwrap_or(Affine2::ZERO);
+            let params =
+                Params::new(width, height, view, composite.opacity, composite.blend).into_array();
+            self.params.extend_from_slice(cast_slice(&params));
+            self.params.resize(
+                self.params.len() + self.params_stride - size_of::<Params>(),
+                0,
+            );
         }
-        let output = match self.surface.get_current_texture() {
-            Ok(output) => output,
-            Err(error) => {
-                match error {
-                    wgpu::SurfaceError::Lost => {
-                        self.surface.configure(&self.device, &self.config);
-                    }
-                    wgpu::SurfaceError::OutOfMemory => {
-                        return Err(());
-                    }
-                    _ => eprintln!("{}", error),
-                };
-                return Ok(());
-            }
+        if !self.params.is_empty() {
+            self.queue
+                .write_buffer(&self.params_buffer, 0, &self.params);
+        }
+
+        layers.sort_draws(&mut self.draws);
+        let (view, resolve_target) = if self.samples > 1 {
+            let multisample = match &mut self.multisample {
+                Some(multisample) if multisample.width == width && multisample.height == height => {
+                    multisample
+                }
+                multisample => multisample.insert(Multisample::new(
+                    &self.device,
+                    format,
+                    self.samples,
+                    width,
+                    height,
+                )),
+            };
+            (&multisample.view, Some(view))
+        } else {
+            (view, None)
         };
-        let view = output
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[RenderPassColorAttachment {
+                view,
+                resolve_target,
+                ops: Operations {
+                    load: LoadOp::Clear(color_convert(clear_color)),
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: None,
+        });
+        pass.set_bind_group(1, &self.atlas_bind_group, &[]);
+        pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+        pass.set_vertex_buffer(2, self.uv_buffer.slice(..));
+        let channels = self.attribute_buffer.iter().chain(&self.color_buffer);
+        for (slot, buffer) in (3..).zip(channels) {
+            pass.set_vertex_buffer(slot, buffer.slice(..));
+        }
+        pass.set_index_buffer(self.index_buffer.slice(..), INDEX_FORMAT);
+        let mut bound = None;
+        let mut blend = None;
+        for draw in self.draws.iter() {
+            if layers.views[draw.chunk].view(camera).is_none() {
+                continue;
+            }
+            let mode = layers.composites[draw.chunk].blend;
+            if blend != Some(mode) {
+                pass.set_pipeline(&self.pipelines[mode as usize]);
+                blend = Some(mode);
+            }
+            if bound != Some(draw.chunk) {
+                let offset = (draw.chunk * self.params_stride) as u32;
+                pass.set_bind_group(0, &self.params_bind_group, &[offset]);
+                bound = Some(draw.chunk);
+            }
+            let index_range = layers.ranges[draw.chunk].index_range32();
+            pass.draw_indexed(index_range, 0, draw.instances.clone());
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_rgba(
+        &mut self,
+        target: &mut Option<Target>,
+        format: TextureFormat,
+        layers: &Layers,
+        width: u32,
+        height: u32,
+        camera: &Camera,
+        clear_color: Color,
+    ) -> Vec<u8> {
+        let target = Target::fit(target, &self.device, format, width, height);
         let mut encoder = self
             .device
-            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
-
-        self.queue
-            .write_buffer(&self.vertex_buffer, 0, cast_slice(&self.layers.vertices));
-        self.queue
-            .write_buffer(&self.index_buffer, 0, cast_slice(&self.layers.indices));
-        self.queue
-            .write_buffer(&self.instance_buffer, 0, cast_slice(&self.layers.instances));
-        self.queue.write_buffer(
-            &self.params_buffer,
-            0,
-            cast_slice(&Params::new(self.config.width, self.config.height, zoom).into_array()),
+            .create_command_encoder(&CommandEncoderDescriptor::default());
+        self.draw(
+            &mut encoder,
+            &target.view,
+            format,
+            layers,
+            target.width,
+            target.height,
+            camera,
+            clear_color,
         );
+        target.copy(&mut encoder);
+        self.queue.submit(once(encoder.finish()));
+        target.read(&self.device, format)
+    }
+}
 
-        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+pub(crate) struct Target {
+    texture: Texture,
+    view: TextureView,
+    buffer: Buffer,
+    width: u32,
+    height: u32,
+    padded_row: u32,
+}
+
+impl Target {
+    fn new(device: &Device, format: TextureFormat, width: u32, height: u32) -> Self {
+        let size = Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let texture = device.create_texture(&TextureDescriptor {
+            label: None,
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format,
+            usage: TextureUsages::RENDER_ATTACHMENT
+                | TextureUsages::COPY_SRC
+                | TextureUsages::TEXTURE_BINDING,
+        });
+        let view = texture.create_view(&Default::default());
+        let padded_row =
+            (width * 4).div_ceil(COPY_BYTES_PER_ROW_ALIGNMENT) * COPY_BYTES_PER_ROW_ALIGNMENT;
+        let buffer = device.create_buffer(&BufferDescriptor {
+            label: None,
+            size: (padded_row * height) as _,
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        Self {
+            texture,
+            view,
+            buffer,
+            width,
+            height,
+            padded_row,
+        }
+    }
+
+    fn fit<'a>(
+        target: &'a mut Option<Self>,
+        device: &Device,
+        format: TextureFormat,
+        width: u32,
+        height: u32,
+    ) -> &'a mut Self {
+        let width = width.max(1);
+        let height = height.max(1);
+        if !matches!(target, Some(target) if target.width == width && target.height == height) {
+            *target = Some(Self::new(device, format, width, height));
+        }
+        target.as_mut().unwrap()
+    }
+
+    fn copy(&self, encoder: &mut CommandEncoder) {
+        encoder.copy_texture_to_buffer(
+            ImageCopyTexture {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: Origin3d::ZERO,
+                aspect: TextureAspect::All,
+            },
+            ImageCopyBuffer {
+                buffer: &self.buffer,
+                layout: ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: NonZeroU32::new(self.padded_row),
+                    rows_per_image: None,
+                },
+            },
+            Extent3d {
+                width: self.width,
+                height: self.height,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+
+    fn read(&self, device: &Device, format: TextureFormat) -> Vec<u8> {
+        let slice = self.buffer.slice(..);
+        let mapping = slice.map_async(MapMode::Read);
+        device.poll(Maintain::Wait);
+        mapping.block_on().unwrap();
+        let mut rgba = Vec::with_capacity((self.width * self.height * 4) as _);
+        for row in slice.get_mapped_range().chunks_exact(self.padded_row as _) {
+            rgba.extend_from_slice(&row[..(self.width * 4) as _]);
+        }
+        self.buffer.unmap();
+        if matches!(
+            format,
+            TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb
+        ) {
+            for pixel in rgba.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+        rgba
+    }
+}
+
+struct Blit {
+    pipeline: RenderPipeline,
+    bind_group_layout: BindGroupLayout,
+}
+
+impl Blit {
+    fn new(device: &Device, format: TextureFormat) -> Self {
+        let shader = device.create_shader_module(&include_wgsl!("blit.wgsl"));
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Texture {
+                    sample_type: TextureSampleType::Float { filterable: false },
+                    view_dimension: TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            }],
+        });
+        let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&layout),
+            vertex: VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: ColorWrites::ALL,
+                }],
+            }),
+            primitive: Default::default(),
+            depth_stencil: None,
+            multisample: Default::default(),
+            multiview: None,
+        });
+        Self {
+            pipeline,
+            bind_group_layout,
+        }
+    }
+
+    fn draw(&self, device: &Device, encoder: &mut CommandEncoder, from: &Target, to: &TextureView) {
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: None,
+            layout: &self.bind_group_layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: BindingResource::TextureView(&from.view),
+            }],
+        });
+        let mut pass = encoder.begin_render_pass(&RenderPassDescriptor {
             label: None,
             color_attachments: &[RenderPassColorAttachment {
-                view: &view,
+                view: to,
                 resolve_target: None,
                 ops: Operations {
-                    load: LoadOp::Clear(color_convert(clear_color)),
+                    load: LoadOp::Load,
                     store: true,
                 },
             }],
             depth_stencil_attachment: None,
         });
         pass.set_pipeline(&self.pipeline);
-        pass.set_bind_group(0, &self.params_bind_group, &[]);
-        pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-        pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
-        pass.set_index_buffer(self.index_buffer.slice(..), IndexFormat::Uint16);
-        for range in self.layers.ranges.iter() {
-            let index_range = range.index_range32();
-            let instance_range = range.instance_range32();
-            pass.draw_indexed(index_range, 0, instance_range);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+}
+
+struct Multisample {
+    view: TextureView,
+    width: u32,
+    height: u32,
+}
+
+impl Multisample {
+    fn new(device: &Device, format: TextureFormat, samples: u32, width: u32, height: u32) -> Self {
+        let texture = device.create_texture(&TextureDescriptor {
+            label: None,
+            size: Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: samples,
+            dimension: TextureDimension::D2,
+            format,
+            usage: TextureUsages::RENDER_ATTACHMENT,
+        });
+        Self {
+            view: texture.create_view(&Default::default()),
+            width,
+            height,
+        }
+    }
+}
+
+enum Backend {
+    Gpu {
+        surface: Surface,
+        config: SurfaceConfiguration,
+        renderer: Box<Renderer>,
+        capture: Option<Target>,
+        blit: Option<Blit>,
+    },
+    Cpu {
+        context: GraphicsContext<Rc<Window>>,
+        raster: Raster,
+        xrgb: Vec<u32>,
+    },
+}
+
+pub(crate) struct Graphics {
+    pub(crate) layers: Layers,
+    backend: Backend,
+}
+
+impl Graphics {
+    pub async fn new(window: &Rc<Window>, layers: Layers, samples: u32) -> Self {
+        let instance = wgpu::Instance::new(Backends::all());
+        let surface = unsafe { instance.create_surface(window.as_ref()) };
+        let size = window.inner_size();
+        if let Some(adapter) = request_adapter(&instance, Some(&surface)).await {
+            let config = SurfaceConfiguration {
+                usage: TextureUsages::RENDER_ATTACHMENT,
+                format: surface.get_preferred_format(&adapter).unwrap(),
+                width: size.width,
+                height: size.height,
+                present_mode: PresentMode::Fifo,
+            };
+            if let Some(renderer) = Renderer::new(&adapter, config.format, &layers, samples).await {
+                surface.configure(&renderer.device, &config);
+                return Self {
+                    layers,
+                    backend: Backend::Gpu {
+                        surface,
+                        config,
+                        renderer: Box::new(renderer),
+                        capture: None,
+                        blit: None,
+                    },
+                };
+            }
+        }
+        log::warn!("no wgpu adapter available, falling back to software rendering");
+        Self {
+            layers,
+            backend: Backend::Cpu {
+                context: unsafe { GraphicsContext::new(window.clone()) }.unwrap(),
+                raster: Raster::with_samples(samples),
+                xrgb: Vec::new(),
+            },
+        }
+    }
+
+    pub fn render(
+        &mut self,
+        size: PhysicalSize<u32>,
+        camera: &Camera,
+        clear_color: Color,
+        capture: bool,
+    ) -> Result<Option<Vec<u8>>, ()> {
+        match &mut self.backend {
+            Backend::Gpu {
+                surface,
+                config,
+                renderer,
+                capture: target,
+                blit,
+            } => {
+                if (size.width != config.width || size.height != config.height)
+                    && size.width != 0
+                    && size.height != 0
+                {
+                    config.width = size.width;
+                    config.height = size.height;
+                    surface.configure(&renderer.device, config);
+                }
+                let output = match surface.get_current_texture() {
+                    Ok(output) => output,
+                    Err(error) => {
+                        match error {
+                            wgpu::SurfaceError::Lost => {
+                                surface.configure(&renderer.device, config);
+                            }
+                            wgpu::SurfaceError::OutOfMemory => {
+                                return Err(());
+                            }
+                            _ => log::warn!("{}", error),
+                        };
+                        return Ok(None);
+                    }
+                };
+                let view = output
+                    .texture
+                    .create_view(&wgpu::TextureViewDescriptor::default());
+                renderer.upload_dirty(&mut self.layers);
+                let mut encoder = renderer
+                    .device
+                    .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+                let capture = (capture
+                    && (size.width, size.height) == (config.width, config.height))
+                    .then(|| {
+                        let target = Target::fit(
+                            target,
+                            &renderer.device,
+                            config.format,
+                            config.width,
+                            config.height,
+                        );
+                        let blit =
+                            blit.get_or_insert_with(|| Blit::new(&renderer.device, config.format));
+                        (target, &*blit)
+                    });
+                let frame = capture.as_ref().map_or(&view, |(target, _)| &target.view);
+                renderer.draw(
+                    &mut encoder,
+                    frame,
+                    config.format,
+                    &self.layers,
+                    config.width,
+                    config.height,
+                    camera,
+                    clear_color,
+                );
+                if let Some((target, blit)) = &capture {
+                    blit.draw(&renderer.device, &mut encoder, target, &view);
+                    target.copy(&mut encoder);
+                }
+                renderer.queue.submit(once(encoder.finish()));
+                output.present();
+                Ok(capture.map(|(target, _)| target.read(&renderer.device, config.format)))
+            }
+            Backend::Cpu {
+                context,
+                raster,
+                xrgb,
+            } => {
+                if size.width == 0 || size.height == 0 {
+                    return Ok(None);
+                }
+                self.layers.clean();
+                let width = size.width.min(u16::MAX as _);
+                let height = size.height.min(u16::MAX as _);
+                raster.render_xrgb(&self.layers, width, height, camera, clear_color, xrgb);
+                context.set_buffer(xrgb, width as _, height as _);
+                Ok(capture.then(|| raster.rgba()))
+            }
         }
-        drop(pass);
-        self.queue.submit(once(encoder.finish()));
-        output.present();
-        Ok(())
     }
 }
 
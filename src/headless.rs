@@ -0,0 +1,116 @@
+use {
+    crate::{
+        camera::Camera,
+        graphics::{request_adapter, Renderer, Target},
+        layers::{Color, Layers},
+        raster::Raster,
+    },
+    pollster::FutureExt as _,
+    wgpu::*,
+};
+
+const FORMAT: TextureFormat = TextureFormat::Rgba8UnormSrgb;
+
+pub struct Headless {
+    renderer: Renderer,
+    target: Option<Target>,
+}
+
+impl Headless {
+    pub fn new(layers: &Layers) -> Option<Self> {
+        Self::with_samples(layers, 1)
+    }
+
+    pub fn with_samples(layers: &Layers, samples: u32) -> Option<Self> {
+        async {
+            let instance = wgpu::Instance::new(Backends::all());
+            let adapter = request_adapter(&instance, None).await?;
+            let renderer = Renderer::new(&adapter, FORMAT, layers, samples).await?;
+            Some(Self {
+                renderer,
+                target: None,
+            })
+        }
+        .block_on()
+    }
+
+    pub fn render(
+        &mut self,
+        layers: &mut Layers,
+        width: u32,
+        height: u32,
+        camera: &Camera,
+        clear_color: Color,
+    ) -> Vec<u8> {
+        self.renderer.upload_dirty(layers);
+        self.draw(layers, width, height, camera, clear_color)
+    }
+
+    fn draw(
+        &mut self,
+        layers: &Layers,
+        width: u32,
+        height: u32,
+        camera: &Camera,
+        clear_color: Color,
+    ) -> Vec<u8> {
+        self.renderer.draw_rgba(
+            &mut self.target,
+            FORMAT,
+            layers,
+            width,
+            height,
+            camera,
+            clear_color,
+        )
+    }
+}
+
+pub fn render_to_rgba(
+    layers: &Layers,
+    width: u32,
+    height: u32,
+    camera: &Camera,
+    clear_color: Color,
+) -> Vec<u8> {
+    match Headless::new(layers) {
+        Some(mut headless) => {
+            headless.renderer.upload(layers);
+            headless.draw(layers, width, height, camera, clear_color)
+        }
+        None => Raster::new().render(layers, width, height, camera, clear_color),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::layers::{instance, layer_bounds},
+        glam::{vec2, Affine2},
+    };
+
+    #[test]
+    fn renders_a_layer() {
+        let mut layers = Layers::new(&[layer_bounds(3, 3, 1)]);
+        let mut layer = layers.get_mut(0).unwrap();
+        layer
+            .set_vertices([vec2(-10.0, -10.0), vec2(0.0, 10.0), vec2(10.0, -10.0)])
+            .unwrap();
+        layer.set_indices([0, 1, 2]).unwrap();
+        layer
+            .set_instances([instance(Affine2::IDENTITY, Color::WHITE)])
+            .unwrap();
+        let mut headless = match Headless::new(&layers) {
+            Some(headless) => headless,
+            None => {
+                eprintln!("no wgpu adapter, skipping");
+                return;
+            }
+        };
+        let rgba = headless.render(&mut layers, 32, 32, &Camera::default(), Color::default());
+        assert_eq!(rgba.len(), 32 * 32 * 4);
+        assert_eq!(rgba[(16 * 32 + 16) * 4..][..4], [255; 4]);
+        assert_eq!(rgba[..4], [0; 4]);
+    }
+}
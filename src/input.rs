@@ -49,6 +49,27 @@ impl Mouse {
         map_mouse_button!(self::is_released(button), false)
     }
 
+    pub fn move_to(&mut self, position: Vec2) {
+        if !self.has_moved {
+            self.velocity = Vec2::ZERO;
+        }
+        self.has_moved = true;
+        self.velocity += position - replace(&mut self.position, position);
+    }
+
+    pub fn scroll_by(&mut self, scroll: Vec2) {
+        self.has_scrolled = true;
+        self.scroll += scroll;
+    }
+
+    pub fn press(&mut self, button: MouseButton) {
+        map_mouse_button!(self::press(button));
+    }
+
+    pub fn release(&mut self, button: MouseButton) {
+        map_mouse_button!(self::release(button));
+    }
+
     pub(crate) fn new() -> Self {
         Self {
             position: Vec2::ZERO,
@@ -66,30 +87,21 @@ impl Mouse {
         match event {
             WindowEvent::CursorEntered { .. } => self.has_entered = true,
             WindowEvent::CursorLeft { .. } => self.has_left = true,
-            WindowEvent::CursorMoved { position, .. } => {
-                self.has_moved = true;
-                self.velocity = replace(
-                    &mut self.position,
-                    vec2(
-                        position.x as f32 - screen_half.x,
-                        screen_half.y - position.y as f32,
-                    ),
-                );
-            }
+            WindowEvent::CursorMoved { position, .. } => self.move_to(vec2(
+                position.x as f32 - screen_half.x,
+                screen_half.y - position.y as f32,
+            )),
             WindowEvent::MouseInput { state, button, .. } => {
                 if *state == ElementState::Pressed {
-                    map_mouse_button!(self::press(button));
+                    self.press(*button);
                 } else {
-                    map_mouse_button!(self::release(button));
+                    self.release(*button);
                 }
             }
-            WindowEvent::MouseWheel { delta, .. } => {
-                self.has_scrolled = true;
-                self.scroll = match delta {
-                    MouseScrollDelta::LineDelta(x, y) => vec2(*x, *y),
-                    MouseScrollDelta::PixelDelta(xy) => vec2(xy.x as _, -(xy.y as f32)),
-                };
-            }
+            WindowEvent::MouseWheel { delta, .. } => self.scroll_by(match delta {
+                MouseScrollDelta::LineDelta(x, y) => vec2(*x, *y),
+                MouseScrollDelta::PixelDelta(xy) => vec2(xy.x as _, -(xy.y as f32)),
+            }),
             _ => return false,
         };
         true
@@ -99,12 +111,10 @@ impl Mouse {
         self.map.unset();
         self.has_entered = false;
         self.has_left = false;
-        if !take(&mut self.has_moved) {
-            self.velocity = Vec2::ZERO;
-        }
-        if !take(&mut self.has_scrolled) {
-            self.scroll = Vec2::ZERO;
-        }
+        self.has_moved = false;
+        self.has_scrolled = false;
+        self.velocity = Vec2::ZERO;
+        self.scroll = Vec2::ZERO;
     }
 }
 
@@ -177,6 +187,14 @@ impl Keys {
         map_keyboard_key!(self::is_released(key))
     }
 
+    pub fn press(&mut self, key: VirtualKeyCode) {
+        map_keyboard_key!(self::press(key))
+    }
+
+    pub fn release(&mut self, key: VirtualKeyCode) {
+        map_keyboard_key!(self::release(key))
+    }
+
     pub(crate) fn new() -> Self {
         Self {
             is_shift: false,
@@ -192,9 +210,9 @@ impl Keys {
             WindowEvent::KeyboardInput { input, .. } if input.virtual_keycode.is_some() => {
                 let keycode = unsafe { input.virtual_keycode.unwrap_unchecked() };
                 if input.state == ElementState::Pressed {
-                    map_keyboard_key!(self::press(keycode))
+                    self.press(keycode)
                 } else {
-                    map_keyboard_key!(self::release(keycode))
+                    self.release(keycode)
                 }
             }
             WindowEvent::ModifiersChanged(state) => {
@@ -255,3 +273,18 @@ impl<const N: usize> Map<N> {
         unsafe { *self.released.get_unchecked(index) }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scroll_accumulates() {
+        let mut mouse = Mouse::new();
+        mouse.scroll_by(vec2(0.0, 1.0));
+        mouse.scroll_by(vec2(0.5, 2.0));
+        assert_eq!(mouse.scroll, vec2(0.5, 3.0));
+        mouse.unset();
+        assert_eq!(mouse.scroll, Vec2::ZERO);
+    }
+}
@@ -1,8 +1,19 @@
 use {
+    crate::{
+        atlas::{Atlas, Region},
+        camera::Camera,
+    },
     bytemuck::{Pod, Zeroable},
-    glam::{Affine2, Vec2},
+    glam::{vec2, Affine2, Vec2},
+    std::{error::Error, fmt},
 };
 
+#[cfg(not(feature = "u32_indices"))]
+pub type Index = u16;
+
+#[cfg(feature = "u32_indices")]
+pub type Index = u32;
+
 pub fn transform(scale: Vec2, angle: f32, translation: Vec2) -> Affine2 {
     Affine2::from_scale_angle_translation(scale, angle, translation)
 }
@@ -16,7 +27,12 @@ pub struct Color {
     pub a: u8,
 }
 
+unsafe impl Pod for Color {}
+unsafe impl Zeroable for Color {}
+
 impl Color {
+    pub const WHITE: Self = color(255, 255, 255, 255);
+
     pub fn new(r: u8, g: u8, b: u8, a: u8) -> Self {
         Self { r, g, b, a }
     }
@@ -42,11 +58,35 @@ pub const fn color(r: u8, g: u8, b: u8, a: u8) -> Color {
     Color { r, g, b, a }
 }
 
+#[repr(C)]
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub struct Attributes {
+    pub normal: Vec2,
+    pub advancement: f32,
+    pub path_id: u32,
+}
+
+unsafe impl Pod for Attributes {}
+unsafe impl Zeroable for Attributes {}
+
+impl Attributes {
+    pub fn new(normal: Vec2, advancement: f32, path_id: u32) -> Self {
+        Self {
+            normal,
+            advancement,
+            path_id,
+        }
+    }
+}
+
 #[repr(C)]
 #[derive(Copy, Clone, PartialEq, Default)]
 pub struct Instance {
     pub transform: [f32; 6],
     pub color: Color,
+    pub region: Region,
+    pub extrude: f32,
+    pub depth: f32,
 }
 
 unsafe impl Pod for Instance {}
@@ -57,6 +97,9 @@ impl Instance {
         Self {
             transform: transform.to_cols_array(),
             color,
+            region: Region::default(),
+            extrude: 0.0,
+            depth: 0.0,
         }
     }
 
@@ -70,38 +113,211 @@ impl Instance {
     pub fn with_color(self, color: Color) -> Self {
         Self { color, ..self }
     }
+
+    pub fn with_region(self, region: Region) -> Self {
+        Self { region, ..self }
+    }
+
+    pub fn with_extrude(self, extrude: f32) -> Self {
+        Self { extrude, ..self }
+    }
+
+    pub fn with_depth(self, depth: f32) -> Self {
+        Self { depth, ..self }
+    }
 }
 
 pub fn instance(transform: Affine2, color: Color) -> Instance {
     Instance::new(transform, color)
 }
 
-#[derive(Copy, Clone, Eq, PartialEq, Default)]
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub enum Space {
+    #[default]
+    World,
+    Screen,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub enum BlendMode {
+    #[default]
+    Alpha,
+    Additive,
+    Multiply,
+    Screen,
+    Premultiplied,
+}
+
+impl BlendMode {
+    pub const ALL: [Self; 5] = [
+        Self::Alpha,
+        Self::Additive,
+        Self::Multiply,
+        Self::Screen,
+        Self::Premultiplied,
+    ];
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct LayerView {
+    pub space: Space,
+    pub parallax: f32,
+    pub transform: Affine2,
+}
+
+impl Default for LayerView {
+    fn default() -> Self {
+        Self::WORLD
+    }
+}
+
+impl LayerView {
+    pub const WORLD: Self = Self::new(Space::World);
+
+    pub const SCREEN: Self = Self::new(Space::Screen);
+
+    pub const fn new(space: Space) -> Self {
+        Self {
+            space,
+            parallax: 1.0,
+            transform: Affine2::IDENTITY,
+        }
+    }
+
+    pub const fn with_parallax(self, parallax: f32) -> Self {
+        Self { parallax, ..self }
+    }
+
+    pub const fn with_transform(self, transform: Affine2) -> Self {
+        Self { transform, ..self }
+    }
+
+    pub(crate) fn view(&self, camera: &Camera) -> Option<Affine2> {
+        match self.space {
+            Space::World => camera.is_visible().then(|| {
+                camera
+                    .with_translation(camera.translation * self.parallax)
+                    .view()
+                    * self.transform
+            }),
+            Space::Screen => Some(self.transform),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub struct LayerBounds {
-    pub max_vertices: u16,
+    pub max_vertices: Index,
     pub max_indices: u32,
     pub max_instances: u32,
+    pub space: Space,
+    pub visible: bool,
+    pub blend: BlendMode,
+    pub attributes: bool,
+    pub colors: bool,
+}
+
+impl Default for LayerBounds {
+    fn default() -> Self {
+        Self::new(0, 0, 0)
+    }
 }
 
 impl LayerBounds {
-    pub const fn new(max_vertices: u16, max_indices: u32, max_instances: u32) -> Self {
+    pub const fn new(max_vertices: Index, max_indices: u32, max_instances: u32) -> Self {
         Self {
             max_vertices,
             max_indices,
             max_instances,
+            space: Space::World,
+            visible: true,
+            blend: BlendMode::Alpha,
+            attributes: false,
+            colors: false,
+        }
+    }
+
+    pub const fn with_space(self, space: Space) -> Self {
+        Self { space, ..self }
+    }
+
+    pub const fn with_visible(self, visible: bool) -> Self {
+        Self { visible, ..self }
+    }
+
+    pub const fn with_blend(self, blend: BlendMode) -> Self {
+        Self { blend, ..self }
+    }
+
+    pub const fn with_attributes(self) -> Self {
+        Self {
+            attributes: true,
+            ..self
+        }
+    }
+
+    pub const fn with_colors(self) -> Self {
+        Self {
+            colors: true,
+            ..self
         }
     }
 }
 
-pub const fn layer_bounds(max_vertices: u16, max_indices: u32, max_instances: u32) -> LayerBounds {
+pub const fn layer_bounds(
+    max_vertices: Index,
+    max_indices: u32,
+    max_instances: u32,
+) -> LayerBounds {
     LayerBounds::new(max_vertices, max_indices, max_instances)
 }
 
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum LayerBuffer {
+    Vertices,
+    Indices,
+    Instances,
+}
+
+impl fmt::Display for LayerBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Vertices => "vertices",
+            Self::Indices => "indices",
+            Self::Instances => "instances",
+        })
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct CapacityError {
+    pub layer: usize,
+    pub buffer: LayerBuffer,
+    pub written: usize,
+    pub dropped: Option<usize>,
+}
+
+impl fmt::Display for CapacityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "layer {} is out of capacity for {}: wrote {}, dropped ",
+            self.layer, self.buffer, self.written
+        )?;
+        match self.dropped {
+            Some(dropped) => write!(f, "{}", dropped),
+            None => f.write_str("an unknown number"),
+        }
+    }
+}
+
+impl Error for CapacityError {}
+
 #[repr(C)]
 #[derive(Copy, Clone, Eq, PartialEq, Default)]
 pub(crate) struct Range {
-    pub(crate) vertex_start: u16,
-    pub(crate) vertex_floating_end: u16,
+    pub(crate) vertex_start: Index,
+    pub(crate) vertex_floating_end: Index,
     pub(crate) index_start: u32,
     pub(crate) instance_start: u32,
     pub(crate) index_floating_end: u32,
@@ -113,6 +329,10 @@ impl Range {
         self.vertex_start as _..self.vertex_floating_end as _
     }
 
+    pub(crate) fn index_range(&self) -> std::ops::Range<usize> {
+        self.index_start as _..self.index_floating_end as _
+    }
+
     pub(crate) fn instance_range(&self) -> std::ops::Range<usize> {
         self.instance_start as _..self.instance_floating_end as _
     }
@@ -126,112 +346,283 @@ impl Range {
     }
 }
 
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub(crate) struct Composite {
+    pub(crate) visible: bool,
+    pub(crate) opacity: f32,
+    pub(crate) blend: BlendMode,
+}
+
+impl Composite {
+    pub(crate) fn is_drawn(&self) -> bool {
+        self.visible && self.opacity > 0.0
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub(crate) struct Channels {
+    pub(crate) attributes: bool,
+    pub(crate) colors: bool,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct Draw {
+    pub(crate) depth: f32,
+    pub(crate) chunk: usize,
+    pub(crate) instances: std::ops::Range<u32>,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub(crate) struct Dirty {
+    pub(crate) vertices: std::ops::Range<usize>,
+    pub(crate) indices: std::ops::Range<usize>,
+    pub(crate) instances: std::ops::Range<usize>,
+}
+
+fn mark(dirty: &mut std::ops::Range<usize>, range: std::ops::Range<usize>) {
+    if range.start >= range.end {
+        return;
+    }
+    if dirty.start >= dirty.end {
+        *dirty = range;
+    } else {
+        dirty.start = dirty.start.min(range.start);
+        dirty.end = dirty.end.max(range.end);
+    }
+}
+
 pub struct Layer<'a> {
-    vertices: &'a [Vec2],
-    instances: &'a [Instance],
-    indices: &'a [u16],
-    range: &'a Range,
-    vertex_end: u16,
-    index_end: u32,
-    instance_end: u32,
+    layers: &'a Layers,
+    chunk: usize,
 }
 
 impl<'a> Layer<'a> {
+    pub fn index(&self) -> usize {
+        self.layers.ranges.len() - self.chunk - 1
+    }
+
     pub fn vertices_len(&self) -> usize {
-        (self.range.vertex_floating_end - self.range.vertex_start) as _
+        self.layers.ranges[self.chunk].vertex_range().len()
     }
 
     pub fn indices_len(&self) -> usize {
-        (self.range.index_floating_end - self.range.index_start) as _
+        self.layers.ranges[self.chunk].index_range().len()
     }
 
     pub fn instances_len(&self) -> usize {
-        (self.range.instance_floating_end - self.range.instance_start) as _
+        self.layers.ranges[self.chunk].instance_range().len()
     }
 
     pub fn max_vertices_len(&self) -> usize {
-        (self.vertex_end - self.range.vertex_start) as _
+        self.layers.vertex_end(self.chunk) - self.layers.ranges[self.chunk].vertex_start as usize
     }
 
     pub fn max_indices_len(&self) -> usize {
-        (self.index_end - self.range.index_start) as _
+        self.layers.index_end(self.chunk) - self.layers.ranges[self.chunk].index_start as usize
     }
 
     pub fn max_instances_len(&self) -> usize {
-        (self.instance_end - self.range.instance_start) as _
+        self.layers.instance_end(self.chunk)
+            - self.layers.ranges[self.chunk].instance_start as usize
     }
 
     pub fn vertices(&self) -> &[Vec2] {
-        unsafe { self.vertices.get_unchecked(self.range.vertex_range()) }
+        &self.layers.vertices[self.layers.ranges[self.chunk].vertex_range()]
+    }
+
+    pub fn uvs(&self) -> &[Vec2] {
+        &self.layers.uvs[self.layers.ranges[self.chunk].vertex_range()]
+    }
+
+    pub fn attributes(&self) -> &[Attributes] {
+        if self.has_attributes() {
+            &self.layers.attributes[self.layers.ranges[self.chunk].vertex_range()]
+        } else {
+            &[]
+        }
+    }
+
+    pub fn colors(&self) -> &[Color] {
+        if self.has_colors() {
+            &self.layers.colors[self.layers.ranges[self.chunk].vertex_range()]
+        } else {
+            &[]
+        }
+    }
+
+    pub fn has_attributes(&self) -> bool {
+        self.layers.channels[self.chunk].attributes
+    }
+
+    pub fn has_colors(&self) -> bool {
+        self.layers.channels[self.chunk].colors
     }
 
-    pub fn get_index(&self, at: usize) -> Option<u16> {
-        self.indices
-            .get(at + self.range.index_start as usize)
-            .map(|i| i - self.range.vertex_start)
+    pub fn get_index(&self, at: usize) -> Option<Index> {
+        let range = &self.layers.ranges[self.chunk];
+        self.layers.indices[range.index_range()]
+            .get(at)
+            .map(|i| i - range.vertex_start)
     }
 
     pub fn instances(&self) -> &[Instance] {
-        unsafe { self.instances.get_unchecked(self.range.instance_range()) }
+        &self.layers.instances[self.layers.ranges[self.chunk].instance_range()]
+    }
+
+    pub fn view(&self) -> LayerView {
+        self.layers.views[self.chunk]
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.layers.composites[self.chunk].visible
+    }
+
+    pub fn opacity(&self) -> f32 {
+        self.layers.composites[self.chunk].opacity
+    }
+
+    pub fn blend(&self) -> BlendMode {
+        self.layers.composites[self.chunk].blend
     }
 }
 
 pub struct LayerMut<'a> {
-    vertices: &'a mut [Vec2],
-    instances: &'a mut [Instance],
-    indices: &'a mut [u16],
-    range: &'a mut Range,
-    vertex_end: u16,
-    index_end: u32,
-    instance_end: u32,
+    layers: &'a mut Layers,
+    chunk: usize,
 }
 
 impl<'a> LayerMut<'a> {
+    pub fn index(&self) -> usize {
+        self.layers.ranges.len() - self.chunk - 1
+    }
+
     pub fn vertices_len(&self) -> usize {
-        (self.range.vertex_floating_end - self.range.vertex_start) as _
+        self.range().vertex_range().len()
     }
 
     pub fn indices_len(&self) -> usize {
-        (self.range.index_floating_end - self.range.index_start) as _
+        self.range().index_range().len()
     }
 
     pub fn instances_len(&self) -> usize {
-        (self.range.instance_floating_end - self.range.instance_start) as _
+        self.range().instance_range().len()
+    }
+
+    pub(crate) fn vertices_room(&self) -> usize {
+        self.layers.vertices_room(self.chunk)
+    }
+
+    pub(crate) fn indices_room(&self) -> usize {
+        self.layers.indices_room(self.chunk)
     }
 
     pub fn max_vertices_len(&self) -> usize {
-        (self.vertex_end - self.range.vertex_start) as _
+        self.layers.vertex_end(self.chunk) - self.range().vertex_start as usize
     }
 
     pub fn max_indices_len(&self) -> usize {
-        (self.index_end - self.range.index_start) as _
+        self.layers.index_end(self.chunk) - self.range().index_start as usize
     }
 
     pub fn max_instances_len(&self) -> usize {
-        (self.instance_end - self.range.instance_start) as _
+        self.layers.instance_end(self.chunk) - self.range().instance_start as usize
     }
 
     pub fn vertices(&self) -> &[Vec2] {
-        unsafe { self.vertices.get_unchecked(self.range.vertex_range()) }
+        &self.layers.vertices[self.range().vertex_range()]
+    }
+
+    pub fn uvs(&self) -> &[Vec2] {
+        &self.layers.uvs[self.range().vertex_range()]
+    }
+
+    pub fn attributes(&self) -> &[Attributes] {
+        if self.has_attributes() {
+            &self.layers.attributes[self.range().vertex_range()]
+        } else {
+            &[]
+        }
+    }
+
+    pub fn colors(&self) -> &[Color] {
+        if self.has_colors() {
+            &self.layers.colors[self.range().vertex_range()]
+        } else {
+            &[]
+        }
     }
 
-    pub fn get_index(&self, at: usize) -> Option<u16> {
-        self.indices
-            .get(at + self.range.index_start as usize)
-            .map(|i| i - self.range.vertex_start)
+    pub fn has_attributes(&self) -> bool {
+        self.layers.channels[self.chunk].attributes
+    }
+
+    pub fn has_colors(&self) -> bool {
+        self.layers.channels[self.chunk].colors
+    }
+
+    pub fn get_index(&self, at: usize) -> Option<Index> {
+        let range = self.range();
+        self.layers.indices[range.index_range()]
+            .get(at)
+            .map(|i| i - range.vertex_start)
     }
 
     pub fn instances(&self) -> &[Instance] {
-        unsafe { self.instances.get_unchecked(self.range.instance_range()) }
+        &self.layers.instances[self.range().instance_range()]
+    }
+
+    pub fn view(&self) -> LayerView {
+        self.layers.views[self.chunk]
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.layers.composites[self.chunk].visible
+    }
+
+    pub fn opacity(&self) -> f32 {
+        self.layers.composites[self.chunk].opacity
+    }
+
+    pub fn blend(&self) -> BlendMode {
+        self.layers.composites[self.chunk].blend
     }
 
     pub fn vertices_mut(&mut self) -> &mut [Vec2] {
-        unsafe { self.vertices.get_unchecked_mut(self.range.vertex_range()) }
+        let range = self.range().vertex_range();
+        mark(&mut self.layers.dirty[self.chunk].vertices, range.clone());
+        &mut self.layers.vertices[range]
+    }
+
+    pub fn uvs_mut(&mut self) -> &mut [Vec2] {
+        let range = self.range().vertex_range();
+        mark(&mut self.layers.dirty[self.chunk].vertices, range.clone());
+        &mut self.layers.uvs[range]
+    }
+
+    pub fn attributes_mut(&mut self) -> &mut [Attributes] {
+        if !self.has_attributes() {
+            return &mut [];
+        }
+        let range = self.range().vertex_range();
+        mark(&mut self.layers.dirty[self.chunk].vertices, range.clone());
+        &mut self.layers.attributes[range]
     }
 
-    pub fn set_index(&mut self, at: usize, value: u16) -> bool {
-        if let Some(dest) = self.indices.get_mut(at) {
-            *dest = value + self.range.vertex_start;
+    pub fn colors_mut(&mut self) -> &mut [Color] {
+        if !self.has_colors() {
+            return &mut [];
+        }
+        let range = self.range().vertex_range();
+        mark(&mut self.layers.dirty[self.chunk].vertices, range.clone());
+        &mut self.layers.colors[range]
+    }
+
+    pub fn set_index(&mut self, at: usize, value: Index) -> bool {
+        let range = *self.range();
+        if let Some(dest) = self.layers.indices[range.index_range()].get_mut(at) {
+            *dest = value + range.vertex_start;
+            let at = range.index_start as usize + at;
+            mark(&mut self.layers.dirty[self.chunk].indices, at..at + 1);
             true
         } else {
             false
@@ -239,160 +630,376 @@ impl<'a> LayerMut<'a> {
     }
 
     pub fn instances_mut(&mut self) -> &mut [Instance] {
-        unsafe {
-            self.instances
-                .get_unchecked_mut(self.range.instance_range())
-        }
+        let range = self.range().instance_range();
+        mark(&mut self.layers.dirty[self.chunk].instances, range.clone());
+        &mut self.layers.instances[range]
+    }
+
+    pub fn set_view(&mut self, view: LayerView) {
+        self.layers.views[self.chunk] = view;
+    }
+
+    pub fn set_visible(&mut self, visible: bool) {
+        self.layers.composites[self.chunk].visible = visible;
+    }
+
+    pub fn set_opacity(&mut self, opacity: f32) {
+        self.layers.composites[self.chunk].opacity = opacity;
+    }
+
+    pub fn set_blend(&mut self, blend: BlendMode) {
+        self.layers.composites[self.chunk].blend = blend;
     }
 
     pub fn clear_vertices(&mut self) {
-        self.range.vertex_floating_end = self.range.vertex_start;
+        let range = self.range_mut();
+        range.vertex_floating_end = range.vertex_start;
     }
 
     pub fn clear_indices(&mut self) {
-        self.range.index_floating_end = self.range.index_start;
+        let range = self.range_mut();
+        range.index_floating_end = range.index_start;
     }
 
     pub fn clear_instances(&mut self) {
-        self.range.instance_floating_end = self.range.instance_start;
+        let range = self.range_mut();
+        range.instance_floating_end = range.instance_start;
     }
 
     pub fn truncate_vertices(&mut self, len: usize) {
-        let offset = self.range.vertex_start + len as u16;
-        if offset < self.vertex_end {
-            return;
+        if len < self.vertices_len() {
+            let range = self.range_mut();
+            range.vertex_floating_end = range.vertex_start + len as Index;
         }
-        self.range.vertex_floating_end = offset;
     }
 
     pub fn truncate_indices(&mut self, len: usize) {
-        let offset = self.range.index_start + len as u32;
-        if offset < self.index_end {
-            return;
+        if len < self.indices_len() {
+            let range = self.range_mut();
+            range.index_floating_end = range.index_start + len as u32;
         }
-        self.range.index_floating_end = offset;
     }
 
     pub fn truncate_instances(&mut self, len: usize) {
-        let offset = self.range.instance_start + len as u32;
-        if offset < self.instance_end {
-            return;
+        if len < self.instances_len() {
+            let range = self.range_mut();
+            range.instance_floating_end = range.instance_start + len as u32;
         }
-        self.range.instance_floating_end = offset;
     }
 
-    pub fn extend_vertices(&mut self, iter: impl IntoIterator<Item = Vec2>) {
-        let slice = unsafe {
-            self.vertices
-                .get_unchecked_mut(self.range.vertex_floating_end as _..self.vertex_end as usize)
-        };
+    pub fn extend_vertices(
+        &mut self,
+        iter: impl IntoIterator<Item = Vec2>,
+    ) -> Result<usize, CapacityError> {
+        self.extend_textured_vertices(iter.into_iter().map(|vertex| (vertex, Vec2::ZERO)))
+    }
+
+    pub fn extend_textured_vertices(
+        &mut self,
+        iter: impl IntoIterator<Item = (Vec2, Vec2)>,
+    ) -> Result<usize, CapacityError> {
+        self.extend_vertex_data(
+            iter.into_iter()
+                .map(|(vertex, uv)| (vertex, uv, Attributes::default(), Color::WHITE)),
+        )
+    }
+
+    pub fn extend_attributed_vertices(
+        &mut self,
+        iter: impl IntoIterator<Item = (Vec2, Attributes)>,
+    ) -> Result<usize, CapacityError> {
+        self.extend_vertex_data(
+            iter.into_iter()
+                .map(|(vertex, attributes)| (vertex, Vec2::ZERO, attributes, Color::WHITE)),
+        )
+    }
+
+    pub fn extend_colored_vertices(
+        &mut self,
+        iter: impl IntoIterator<Item = (Vec2, Color)>,
+    ) -> Result<usize, CapacityError> {
+        self.extend_vertex_data(
+            iter.into_iter()
+                .map(|(vertex, color)| (vertex, Vec2::ZERO, Attributes::default(), color)),
+        )
+    }
+
+    pub(crate) fn extend_vertex_data(
+        &mut self,
+        iter: impl IntoIterator<Item = (Vec2, Vec2, Attributes, Color)>,
+    ) -> Result<usize, CapacityError> {
         let mut additional = iter.into_iter();
-        for dest in slice.iter_mut() {
-            if let Some(src) = additional.next() {
-                *dest = src;
-                self.range.vertex_floating_end += 1;
-            } else {
-                return;
+        let mut written = 0;
+        while let Some(first) = additional.next() {
+            if !self
+                .layers
+                .reserve_vertices(self.chunk, 1 + additional.size_hint().0)
+            {
+                let dropped = additional.size_hint().1.map(|n| n + 1);
+                return Err(self.overflow(LayerBuffer::Vertices, written, dropped));
             }
+            let end = self.layers.vertex_end(self.chunk);
+            let channels = self.layers.channels[self.chunk];
+            let range = &mut self.layers.ranges[self.chunk];
+            let start = range.vertex_floating_end as usize;
+            let dests = self.layers.vertices[start..end]
+                .iter_mut()
+                .zip(&mut self.layers.uvs[start..end])
+                .zip(start..end);
+            let (attributes, colors) = (&mut self.layers.attributes, &mut self.layers.colors);
+            let len = fill(
+                dests,
+                first,
+                &mut additional,
+                |((vertex, uv), at), (src_vertex, src_uv, src_attributes, src_color)| {
+                    (*vertex, *uv) = (src_vertex, src_uv);
+                    if channels.attributes {
+                        attributes[at] = src_attributes;
+                    }
+                    if channels.colors {
+                        colors[at] = src_color;
+                    }
+                },
+            );
+            range.vertex_floating_end += len as Index;
+            mark(
+                &mut self.layers.dirty[self.chunk].vertices,
+                start..start + len,
+            );
+            written += len;
         }
+        Ok(written)
     }
 
-    pub fn extend_indices(&mut self, iter: impl IntoIterator<Item = u16>) {
-        let slice = unsafe {
-            self.indices
-                .get_unchecked_mut(self.range.index_floating_end as _..self.index_end as usize)
-        };
-        let mut additional = iter.into_iter();
-        for dest in slice.iter_mut() {
-            if let Some(src) = additional.next() {
-                *dest = src + self.range.vertex_start;
-                self.range.index_floating_end += 1;
-            } else {
-                return;
+    pub fn extend_indices(
+        &mut self,
+        iter: impl IntoIterator<Item = Index>,
+    ) -> Result<usize, CapacityError> {
+        let vertex_start = self.range().vertex_start;
+        let mut additional = iter.into_iter().map(|i| i + vertex_start);
+        let mut written = 0;
+        while let Some(first) = additional.next() {
+            if !self
+                .layers
+                .reserve_indices(self.chunk, 1 + additional.size_hint().0)
+            {
+                let dropped = additional.size_hint().1.map(|n| n + 1);
+                return Err(self.overflow(LayerBuffer::Indices, written, dropped));
             }
+            let end = self.layers.index_end(self.chunk);
+            let range = &mut self.layers.ranges[self.chunk];
+            let start = range.index_floating_end as usize;
+            let dests = self.layers.indices[start..end].iter_mut();
+            let len = fill(dests, first, &mut additional, |dest, src| *dest = src);
+            range.index_floating_end += len as u32;
+            mark(
+                &mut self.layers.dirty[self.chunk].indices,
+                start..start + len,
+            );
+            written += len;
         }
+        Ok(written)
     }
 
-    pub fn extend_instances(&mut self, iter: impl IntoIterator<Item = Instance>) {
-        let slice = unsafe {
-            self.instances.get_unchecked_mut(
-                self.range.instance_floating_end as _..self.instance_end as usize,
-            )
-        };
+    pub fn extend_instances(
+        &mut self,
+        iter: impl IntoIterator<Item = Instance>,
+    ) -> Result<usize, CapacityError> {
         let mut additional = iter.into_iter();
-        for dest in slice.iter_mut() {
-            if let Some(src) = additional.next() {
-                *dest = src;
-                self.range.instance_floating_end += 1;
-            } else {
-                return;
+        let mut written = 0;
+        while let Some(first) = additional.next() {
+            if !self
+                .layers
+                .reserve_instances(self.chunk, 1 + additional.size_hint().0)
+            {
+                let dropped = additional.size_hint().1.map(|n| n + 1);
+                return Err(self.overflow(LayerBuffer::Instances, written, dropped));
             }
+            let end = self.layers.instance_end(self.chunk);
+            let range = &mut self.layers.ranges[self.chunk];
+            let start = range.instance_floating_end as usize;
+            let dests = self.layers.instances[start..end].iter_mut();
+            let len = fill(dests, first, &mut additional, |dest, src| *dest = src);
+            range.instance_floating_end += len as u32;
+            mark(
+                &mut self.layers.dirty[self.chunk].instances,
+                start..start + len,
+            );
+            written += len;
         }
+        Ok(written)
+    }
+
+    pub fn set_vertices(
+        &mut self,
+        iter: impl IntoIterator<Item = Vec2>,
+    ) -> Result<usize, CapacityError> {
+        self.clear_vertices();
+        self.extend_vertices(iter)
     }
 
-    pub fn set_vertices(&mut self, iter: impl IntoIterator<Item = Vec2>) {
+    pub fn set_textured_vertices(
+        &mut self,
+        iter: impl IntoIterator<Item = (Vec2, Vec2)>,
+    ) -> Result<usize, CapacityError> {
         self.clear_vertices();
-        self.extend_vertices(iter);
+        self.extend_textured_vertices(iter)
+    }
+
+    pub fn set_attributed_vertices(
+        &mut self,
+        iter: impl IntoIterator<Item = (Vec2, Attributes)>,
+    ) -> Result<usize, CapacityError> {
+        self.clear_vertices();
+        self.extend_attributed_vertices(iter)
+    }
+
+    pub fn set_colored_vertices(
+        &mut self,
+        iter: impl IntoIterator<Item = (Vec2, Color)>,
+    ) -> Result<usize, CapacityError> {
+        self.clear_vertices();
+        self.extend_colored_vertices(iter)
+    }
+
+    pub fn extend_quad(&mut self, size: Vec2) -> Result<usize, CapacityError> {
+        let first = self.vertices_len() as Index;
+        let half = size * 0.5;
+        self.extend_textured_vertices([
+            (vec2(-half.x, half.y), vec2(0.0, 0.0)),
+            (vec2(half.x, half.y), vec2(1.0, 0.0)),
+            (vec2(half.x, -half.y), vec2(1.0, 1.0)),
+            (vec2(-half.x, -half.y), vec2(0.0, 1.0)),
+        ])?;
+        self.extend_indices([0, 1, 2, 0, 2, 3].map(|i| first + i))
     }
 
-    pub fn set_indices(&mut self, iter: impl IntoIterator<Item = u16>) {
+    pub fn set_indices(
+        &mut self,
+        iter: impl IntoIterator<Item = Index>,
+    ) -> Result<usize, CapacityError> {
         self.clear_indices();
-        self.extend_indices(iter);
+        self.extend_indices(iter)
     }
 
-    pub fn set_instances(&mut self, iter: impl IntoIterator<Item = Instance>) {
+    pub fn set_instances(
+        &mut self,
+        iter: impl IntoIterator<Item = Instance>,
+    ) -> Result<usize, CapacityError> {
         self.clear_instances();
-        self.extend_instances(iter);
+        self.extend_instances(iter)
+    }
+
+    fn overflow(
+        &mut self,
+        buffer: LayerBuffer,
+        written: usize,
+        dropped: Option<usize>,
+    ) -> CapacityError {
+        let layer = self.index();
+        let error = CapacityError {
+            layer,
+            buffer,
+            written,
+            dropped,
+        };
+        match self
+            .layers
+            .overflows
+            .iter_mut()
+            .find(|e| e.layer == layer && e.buffer == buffer)
+        {
+            Some(summary) => {
+                summary.written += written;
+                summary.dropped = summary.dropped.zip(dropped).map(|(a, b)| a + b);
+            }
+            None => self.layers.overflows.push(error),
+        }
+        error
+    }
+
+    fn range(&self) -> &Range {
+        &self.layers.ranges[self.chunk]
+    }
+
+    fn range_mut(&mut self) -> &mut Range {
+        &mut self.layers.ranges[self.chunk]
     }
 }
 
+fn fill<D, T>(
+    dests: impl IntoIterator<Item = D>,
+    first: T,
+    additional: &mut impl Iterator<Item = T>,
+    mut write: impl FnMut(D, T),
+) -> usize {
+    let mut dests = dests.into_iter();
+    if let Some(dest) = dests.next() {
+        write(dest, first);
+    } else {
+        return 0;
+    }
+    let mut len = 1;
+    for dest in dests {
+        if let Some(src) = additional.next() {
+            write(dest, src);
+            len += 1;
+        } else {
+            break;
+        }
+    }
+    len
+}
+
+fn insert_gap<T: Copy>(slab: &mut Box<[T]>, at: usize, len: usize, value: T) {
+    let mut grown = Vec::with_capacity(slab.len() + len);
+    grown.extend_from_slice(&slab[..at]);
+    grown.resize(at + len, value);
+    grown.extend_from_slice(&slab[at..]);
+    *slab = grown.into_boxed_slice();
+}
+
+const MIN_GROWTH: usize = 4;
+
+fn growth(capacity: usize, free: usize, wanted: usize, remaining: usize) -> usize {
+    (wanted - free).max(capacity).max(MIN_GROWTH).min(remaining)
+}
+
 pub struct Layers {
     pub(crate) vertices: Box<[Vec2]>,
-    pub(crate) indices: Box<[u16]>,
+    pub(crate) uvs: Box<[Vec2]>,
+    pub(crate) attributes: Box<[Attributes]>,
+    pub(crate) colors: Box<[Color]>,
+    pub(crate) indices: Box<[Index]>,
     pub(crate) instances: Box<[Instance]>,
     pub(crate) ranges: Box<[Range]>,
-}
-
-macro_rules! get {
-    ($name:ident($($mut:ident)? &$self:ident::$get_range:ident($index:expr))) => {{
-        let chunk = $self.ranges.len().checked_sub($index + 1)?;
-        let (vertex_end, index_end, instance_end) = {
-            let next_chunk = chunk + 1;
-            if next_chunk == $self.ranges.len() {
-                (
-                    $self.vertices.len() as _,
-                    $self.indices.len() as _,
-                    $self.instances.len() as _,
-                )
-            } else {
-                let next_range = unsafe { $self.ranges.get_unchecked(next_chunk) };
-                (
-                    next_range.vertex_start,
-                    next_range.index_start,
-                    next_range.instance_start,
-                )
-            }
-        };
-        let range = unsafe { $self.ranges.$get_range(chunk) };
-        Some($name {
-            vertices: &$($mut)? $self.vertices,
-            indices: &$($mut)? $self.indices,
-            instances: &$($mut)? $self.instances,
-            range,
-            vertex_end,
-            index_end,
-            instance_end,
-        })
-    }};
+    pub(crate) views: Box<[LayerView]>,
+    pub(crate) orders: Box<[f32]>,
+    pub(crate) composites: Box<[Composite]>,
+    pub(crate) channels: Box<[Channels]>,
+    pub(crate) dirty: Box<[Dirty]>,
+    pub(crate) relayout: bool,
+    pub(crate) overflows: Vec<CapacityError>,
+    pub(crate) atlas: Atlas,
 }
 
 impl Layers {
-    pub fn get(&self, index: usize) -> Option<Layer> {
-        get!(Layer(&self::get_unchecked(index)))
+    pub const MAX_VERTICES: usize = Index::MAX as _;
+
+    pub fn get(&self, index: usize) -> Option<Layer<'_>> {
+        let chunk = self.ranges.len().checked_sub(index + 1)?;
+        Some(Layer {
+            layers: self,
+            chunk,
+        })
     }
 
-    pub fn get_mut(&mut self, index: usize) -> Option<LayerMut> {
-        get!(LayerMut(mut &self::get_unchecked_mut(index)))
+    pub fn get_mut(&mut self, index: usize) -> Option<LayerMut<'_>> {
+        let chunk = self.ranges.len().checked_sub(index + 1)?;
+        Some(LayerMut {
+            layers: self,
+            chunk,
+        })
     }
 
     pub fn len(&self) -> usize {
@@ -403,32 +1010,401 @@ impl Layers {
         self.ranges.is_empty()
     }
 
-    pub(crate) fn new(bounds: &[LayerBounds]) -> Self {
+    pub fn order(&self, index: usize) -> Option<f32> {
+        let chunk = self.ranges.len().checked_sub(index + 1)?;
+        Some(self.orders[chunk])
+    }
+
+    pub fn set_order(&mut self, index: usize, order: f32) -> bool {
+        match self.ranges.len().checked_sub(index + 1) {
+            Some(chunk) => {
+                self.orders[chunk] = order;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn overflows(&self) -> &[CapacityError] {
+        &self.overflows
+    }
+
+    pub fn atlas(&self) -> &Atlas {
+        &self.atlas
+    }
+
+    pub fn atlas_mut(&mut self) -> &mut Atlas {
+        &mut self.atlas
+    }
+
+    pub fn new(bounds: &[LayerBounds]) -> Self {
         let mut ranges = vec![Range::default(); bounds.len()].into_boxed_slice();
-        let mut vertex_start = 0;
+        let mut vertex_start = 0usize;
         let mut index_start = 0;
         let mut instance_start = 0;
         for i in 0..bounds.len() {
-            let range = unsafe { ranges.get_unchecked_mut(i) };
-            range.vertex_start = vertex_start;
-            range.vertex_floating_end = vertex_start;
+            let range = &mut ranges[i];
+            range.vertex_start = vertex_start as _;
+            range.vertex_floating_end = vertex_start as _;
             range.index_start = index_start;
             range.index_floating_end = index_start;
             range.instance_start = instance_start;
             range.instance_floating_end = instance_start;
-            let bound = unsafe { bounds.get_unchecked(bounds.len() - i - 1) };
-            vertex_start += bound.max_vertices;
+            let bound = &bounds[bounds.len() - i - 1];
+            vertex_start = (vertex_start + bound.max_vertices as usize).min(Self::MAX_VERTICES);
             index_start += bound.max_indices;
             instance_start += bound.max_instances;
         }
-        let vertices = vec![Vec2::ZERO; vertex_start as _].into_boxed_slice();
-        let indices = vec![0u16; index_start as _].into_boxed_slice();
+        let vertices = vec![Vec2::ZERO; vertex_start].into_boxed_slice();
+        let uvs = vertices.clone();
+        let channels: Box<[Channels]> = bounds
+            .iter()
+            .rev()
+            .map(|bound| Channels {
+                attributes: bound.attributes,
+                colors: bound.colors,
+            })
+            .collect();
+        let slab_len = |enabled: bool| if enabled { vertex_start } else { 0 };
+        let attributes = vec![
+            Attributes::default();
+            slab_len(channels.iter().any(|channels| channels.attributes))
+        ]
+        .into_boxed_slice();
+        let colors = vec![Color::WHITE; slab_len(channels.iter().any(|channels| channels.colors))]
+            .into_boxed_slice();
+        let indices = vec![0 as Index; index_start as _].into_boxed_slice();
         let instances = vec![Instance::default(); instance_start as _].into_boxed_slice();
         Self {
             vertices,
+            uvs,
+            attributes,
+            colors,
             indices,
             instances,
+            views: bounds
+                .iter()
+                .rev()
+                .map(|bound| LayerView::new(bound.space))
+                .collect(),
+            orders: (0..bounds.len()).rev().map(|index| index as f32).collect(),
+            composites: bounds
+                .iter()
+                .rev()
+                .map(|bound| Composite {
+                    visible: bound.visible,
+                    opacity: 1.0,
+                    blend: bound.blend,
+                })
+                .collect(),
+            channels,
+            dirty: vec![Dirty::default(); ranges.len()].into_boxed_slice(),
+            relayout: true,
             ranges,
+            overflows: Vec::new(),
+            atlas: Atlas::default(),
+        }
+    }
+
+    pub(crate) fn has_attributes(&self) -> bool {
+        self.channels.iter().any(|channels| channels.attributes)
+    }
+
+    pub(crate) fn has_colors(&self) -> bool {
+        self.channels.iter().any(|channels| channels.colors)
+    }
+
+    pub(crate) fn clear_overflows(&mut self) {
+        self.overflows.clear();
+    }
+
+    pub(crate) fn unset(&mut self) {
+        for overflow in &self.overflows {
+            log::warn!("{}", overflow);
+        }
+    }
+
+    pub(crate) fn sort_draws(&self, draws: &mut Vec<Draw>) {
+        draws.clear();
+        for (chunk, range) in self.ranges.iter().enumerate() {
+            if range.index_range().is_empty() || !self.composites[chunk].is_drawn() {
+                continue;
+            }
+            let order = self.orders[chunk];
+            let instances = &self.instances[range.instance_range()];
+            let mut start = 0;
+            while start < instances.len() {
+                let depth = instances[start].depth;
+                let len = 1 + instances[start + 1..]
+                    .iter()
+                    .take_while(|instance| instance.depth == depth)
+                    .count();
+                let first = range.instance_start + start as u32;
+                draws.push(Draw {
+                    depth: order + depth,
+                    chunk,
+                    instances: first..first + len as u32,
+                });
+                start += len;
+            }
+        }
+        draws.sort_by(|a, b| b.depth.total_cmp(&a.depth));
+        draws.dedup_by(|next, draw| {
+            let merges = draw.chunk == next.chunk && draw.instances.end == next.instances.start;
+            if merges {
+                draw.instances.end = next.instances.end;
+            }
+            merges
+        });
+    }
+
+    pub(crate) fn clean(&mut self) {
+        self.dirty.fill(Dirty::default());
+        self.relayout = false;
+        self.atlas.clean();
+    }
+
+    pub(crate) fn vertex_end(&self, chunk: usize) -> usize {
+        self.ranges
+            .get(chunk + 1)
+            .map_or(self.vertices.len(), |next| next.vertex_start as _)
+    }
+
+    pub(crate) fn index_end(&self, chunk: usize) -> usize {
+        self.ranges
+            .get(chunk + 1)
+            .map_or(self.indices.len(), |next| next.index_start as _)
+    }
+
+    pub(crate) fn instance_end(&self, chunk: usize) -> usize {
+        self.ranges
+            .get(chunk + 1)
+            .map_or(self.instances.len(), |next| next.instance_start as _)
+    }
+
+    pub(crate) fn vertices_room(&self, chunk: usize) -> usize {
+        let free = self.vertex_end(chunk) - self.ranges[chunk].vertex_floating_end as usize;
+        free + (Self::MAX_VERTICES - self.vertices.len())
+    }
+
+    pub(crate) fn indices_room(&self, chunk: usize) -> usize {
+        let free = self.index_end(chunk) - self.ranges[chunk].index_floating_end as usize;
+        free + (u32::MAX as usize - self.indices.len())
+    }
+
+    pub(crate) fn reserve_vertices(&mut self, chunk: usize, additional: usize) -> bool {
+        let range = self.ranges[chunk];
+        let end = self.vertex_end(chunk);
+        let free = end - range.vertex_floating_end as usize;
+        if free >= additional {
+            return true;
+        }
+        let gap = growth(
+            end - range.vertex_start as usize,
+            free,
+            additional,
+            Self::MAX_VERTICES - self.vertices.len(),
+        );
+        if gap != 0 {
+            self.relayout = true;
+            insert_gap(&mut self.vertices, end, gap, Vec2::ZERO);
+            insert_gap(&mut self.uvs, end, gap, Vec2::ZERO);
+            if self.has_attributes() {
+                insert_gap(&mut self.attributes, end, gap, Attributes::default());
+            }
+            if self.has_colors() {
+                insert_gap(&mut self.colors, end, gap, Color::WHITE);
+            }
+            for next in self.ranges[chunk + 1..].iter_mut() {
+                next.vertex_start += gap as Index;
+                next.vertex_floating_end += gap as Index;
+                for index in self.indices[next.index_range()].iter_mut() {
+                    *index += gap as Index;
+                }
+            }
+        }
+        free + gap != 0
+    }
+
+    pub(crate) fn reserve_indices(&mut self, chunk: usize, additional: usize) -> bool {
+        let range = self.ranges[chunk];
+        let end = self.index_end(chunk);
+        let free = end - range.index_floating_end as usize;
+        if free >= additional {
+            return true;
+        }
+        let gap = growth(
+            end - range.index_start as usize,
+            free,
+            additional,
+            u32::MAX as usize - self.indices.len(),
+        );
+        if gap != 0 {
+            self.relayout = true;
+            insert_gap(&mut self.indices, end, gap, 0);
+            for next in self.ranges[chunk + 1..].iter_mut() {
+                next.index_start += gap as u32;
+                next.index_floating_end += gap as u32;
+            }
+        }
+        free + gap != 0
+    }
+
+    pub(crate) fn reserve_instances(&mut self, chunk: usize, additional: usize) -> bool {
+        let range = self.ranges[chunk];
+        let end = self.instance_end(chunk);
+        let free = end - range.instance_floating_end as usize;
+        if free >= additional {
+            return true;
+        }
+        let gap = growth(
+            end - range.instance_start as usize,
+            free,
+            additional,
+            u32::MAX as usize - self.instances.len(),
+        );
+        if gap != 0 {
+            self.relayout = true;
+            insert_gap(&mut self.instances, end, gap, Instance::default());
+            for next in self.ranges[chunk + 1..].iter_mut() {
+                next.instance_start += gap as u32;
+                next.instance_floating_end += gap as u32;
+            }
+        }
+        free + gap != 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grow_past_bounds() {
+        let mut layers = Layers::new(&[layer_bounds(2, 3, 1), layer_bounds(2, 3, 1)]);
+        let mut top = layers.get_mut(0).unwrap();
+        top.set_vertices([vec2(1.0, 1.0), vec2(2.0, 2.0)]).unwrap();
+        top.set_indices([0, 1, 1]).unwrap();
+        layers.clean();
+
+        let mut bottom = layers.get_mut(1).unwrap();
+        let vertices = (0..5).map(|i| vec2(i as f32, 0.0));
+        assert_eq!(bottom.set_vertices(vertices), Ok(5));
+        assert_eq!(bottom.set_indices([0, 1, 2, 2, 3, 4, 4]), Ok(7));
+        assert_eq!(bottom.set_instances([Instance::default(); 3]), Ok(3));
+        assert_eq!(bottom.max_vertices_len(), 6);
+        assert_eq!(bottom.max_indices_len(), 7);
+        assert_eq!(bottom.max_instances_len(), 5);
+        assert!(layers.relayout);
+
+        let top = layers.get(0).unwrap();
+        assert_eq!(top.vertices(), [vec2(1.0, 1.0), vec2(2.0, 2.0)]);
+        assert_eq!(
+            (0..3).map(|at| top.get_index(at)).collect::<Vec<_>>(),
+            [Some(0), Some(1), Some(1)]
+        );
+        assert_eq!(top.max_vertices_len(), 2);
+        assert_eq!(layers.indices[layers.ranges[1].index_range()], [6, 7, 7]);
+    }
+
+    #[test]
+    fn grow_at_least_by_capacity() {
+        let mut layers = Layers::new(&[layer_bounds(4, 0, 0)]);
+        let mut layer = layers.get_mut(0).unwrap();
+        layer.set_vertices([Vec2::ZERO; 5]).unwrap();
+        assert_eq!(layer.vertices_len(), 5);
+        assert_eq!(layer.max_vertices_len(), 8);
+    }
+
+    #[test]
+    fn grow_empty_layer_without_size_hint() {
+        let mut layers = Layers::new(&[layer_bounds(0, 0, 0)]);
+        let mut layer = layers.get_mut(0).unwrap();
+        let vertices = (0..200).filter(|i| i % 2 == 0).map(|i| vec2(i as f32, 0.0));
+        assert_eq!(layer.extend_vertices(vertices), Ok(100));
+        assert_eq!(layer.vertices_len(), 100);
+        assert_eq!(layer.max_vertices_len(), 128);
+    }
+
+    #[test]
+    fn mark_dirty_ranges() {
+        let mut layers = Layers::new(&[layer_bounds(4, 6, 2), layer_bounds(4, 6, 2)]);
+        layers.clean();
+        let mut top = layers.get_mut(0).unwrap();
+        top.set_instances([Instance::default()]).unwrap();
+        top.extend_vertices([Vec2::ZERO]).unwrap();
+        top.extend_vertices([Vec2::ONE; 2]).unwrap();
+        assert!(!layers.relayout);
+        assert_eq!(layers.dirty[0], Dirty::default());
+        assert_eq!(layers.dirty[1].vertices, 4..7);
+        assert_eq!(layers.dirty[1].indices, 0..0);
+        assert_eq!(layers.dirty[1].instances, 2..3);
+
+        layers.clean();
+        let mut bottom = layers.get_mut(1).unwrap();
+        bottom.set_indices([0, 1, 2]).unwrap();
+        bottom.set_index(4, 3);
+        bottom.set_index(1, 3);
+        assert_eq!(layers.dirty[0].indices, 0..3);
+        assert_eq!(layers.dirty[1], Dirty::default());
+    }
+
+    #[test]
+    fn mark_merges_ranges() {
+        let mut dirty = 0..0;
+        mark(&mut dirty, 4..4);
+        assert!(dirty.is_empty());
+        mark(&mut dirty, 5..8);
+        assert_eq!(dirty, 5..8);
+        mark(&mut dirty, 1..2);
+        assert_eq!(dirty, 1..8);
+        mark(&mut dirty, 3..4);
+        assert_eq!(dirty, 1..8);
+    }
+
+    #[test]
+    fn sort_draws_back_to_front() {
+        let mut layers = Layers::new(&[
+            layer_bounds(3, 3, 3),
+            layer_bounds(3, 3, 1),
+            layer_bounds(3, 3, 1),
+        ]);
+        for index in 0..3 {
+            let mut layer = layers.get_mut(index).unwrap();
+            layer.set_vertices([Vec2::ZERO; 3]).unwrap();
+            layer.set_indices([0, 1, 2]).unwrap();
         }
+        let mut top = layers.get_mut(0).unwrap();
+        let depths = [2.0, 0.5, 0.0];
+        top.set_instances(depths.map(|depth| Instance::default().with_depth(depth)))
+            .unwrap();
+        let mut middle = layers.get_mut(1).unwrap();
+        middle.set_instances([Instance::default()]).unwrap();
+        let mut bottom = layers.get_mut(2).unwrap();
+        bottom.set_instances([Instance::default()]).unwrap();
+        bottom.set_opacity(0.0);
+
+        let mut draws = Vec::new();
+        layers.sort_draws(&mut draws);
+        assert_eq!(
+            draws,
+            [
+                Draw {
+                    depth: 2.0,
+                    chunk: 2,
+                    instances: 2..3,
+                },
+                Draw {
+                    depth: 1.0,
+                    chunk: 1,
+                    instances: 1..2,
+                },
+                Draw {
+                    depth: 0.5,
+                    chunk: 2,
+                    instances: 3..5,
+                },
+            ]
+        );
     }
 }
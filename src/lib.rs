@@ -1,15 +1,31 @@
 #![allow(dead_code)]
+mod atlas;
+mod camera;
+mod capture;
+mod gradient;
 mod graphics;
+mod headless;
 mod input;
 mod layers;
+mod pan_zoom;
+mod raster;
+mod replay;
 mod screen;
+mod snapshot;
+mod stroke;
+mod svg;
 mod tesselator;
+mod text;
 
 use {
+    capture::Capture,
     graphics::Graphics,
     pollster::FutureExt as _,
+    replay::Journal,
     std::{
         mem::replace,
+        path::Path,
+        rc::Rc,
         time::{Duration, Instant},
     },
     winit::{
@@ -21,14 +37,25 @@ use {
 };
 
 pub use {
+    atlas::{Atlas, Region},
+    camera::{camera, Camera},
     glam::{const_mat2, const_vec2, mat2, vec2, Affine2, Mat2, Vec2, Vec2Swizzles},
+    gradient::Gradient,
+    headless::{render_to_rgba, Headless},
     input::{Keys, Mouse},
     layers::{
-        color, instance, layer_bounds, transform, Color, Instance, Layer, LayerBounds, LayerMut,
-        Layers,
+        color, instance, layer_bounds, transform, Attributes, BlendMode, CapacityError, Color,
+        Index, Instance, Layer, LayerBounds, LayerBuffer, LayerMut, LayerView, Layers, Space,
     },
+    lyon_tessellation::{LineCap, LineJoin},
+    pan_zoom::{pan_zoom, PanZoom},
+    raster::Raster,
     screen::Screen,
-    tesselator::{tessellation_chain, TessellationChain, Tessellator},
+    snapshot::{snapshot, Backend, Script, Snapshot},
+    stroke::{stroke_style, StrokeStyle},
+    svg::{load_svg, parse_svg, parse_svg_path, SvgError, SvgShape},
+    tesselator::{tessellation_chain, Shape, TessellationChain, TessellationError, Tessellator},
+    text::{Align, Font},
 };
 
 pub struct Setup<'a> {
@@ -36,6 +63,11 @@ pub struct Setup<'a> {
     pub width: u32,
     pub height: u32,
     pub layers_bounds: &'a [LayerBounds],
+    pub timestep: Option<Duration>,
+    pub input_record: Option<&'a Path>,
+    pub input_replay: Option<&'a Path>,
+    pub samples: u32,
+    pub pan_zoom: Option<PanZoom>,
 }
 
 pub fn setup<'a>(
@@ -54,6 +86,7 @@ pub struct State<'a> {
     pub mouse: &'a Mouse,
     pub keys: &'a Keys,
     pub delta: &'a Duration,
+    pub alpha: f32,
 }
 
 impl<'a> Setup<'a> {
@@ -63,6 +96,44 @@ impl<'a> Setup<'a> {
             width,
             height,
             layers_bounds,
+            timestep: None,
+            input_record: None,
+            input_replay: None,
+            samples: 1,
+            pan_zoom: None,
+        }
+    }
+
+    pub fn with_timestep(self, timestep: Duration) -> Self {
+        Self {
+            timestep: Some(timestep).filter(|t| !t.is_zero()),
+            ..self
+        }
+    }
+
+    pub fn with_input_record(self, path: &'a Path) -> Self {
+        Self {
+            input_record: Some(path),
+            ..self
+        }
+    }
+
+    pub fn with_input_replay(self, path: &'a Path) -> Self {
+        Self {
+            input_replay: Some(path),
+            ..self
+        }
+    }
+
+    /// Only 1 and 4 samples are supported, any count above 1 is clamped to 4.
+    pub fn with_samples(self, samples: u32) -> Self {
+        Self { samples, ..self }
+    }
+
+    pub fn with_pan_zoom(self, pan_zoom: PanZoom) -> Self {
+        Self {
+            pan_zoom: Some(pan_zoom),
+            ..self
         }
     }
 
@@ -70,56 +141,105 @@ impl<'a> Setup<'a> {
     pub fn run(self, mut f: impl FnMut(State<'_>) + 'static) -> ! {
         env_logger::init();
         let event_loop = EventLoop::new();
-        let window = if self.width != 0 && self.height != 0 {
-            WindowBuilder::new().with_inner_size(PhysicalSize::new(self.width, self.height))
-        } else {
-            WindowBuilder::new()
-        }
-        .with_visible(false)
-        .with_title(self.title)
-        .build(&event_loop)
-        .unwrap();
+        let window = Rc::new(
+            if self.width != 0 && self.height != 0 {
+                WindowBuilder::new().with_inner_size(PhysicalSize::new(self.width, self.height))
+            } else {
+                WindowBuilder::new()
+            }
+            .with_visible(false)
+            .with_title(self.title)
+            .build(&event_loop)
+            .unwrap(),
+        );
         let mut frame = Instant::now();
         let mut delta = Duration::ZERO;
+        let mut accumulator = Duration::ZERO;
+        let mut tick = 0;
         let mut size = window.inner_size();
         let mut screen = Screen::new(size);
         let mut mouse = Mouse::new();
         let mut keys = Keys::new();
-        let mut graphics = Graphics::new(&window, Layers::new(self.layers_bounds)).block_on();
+        let mut graphics =
+            Graphics::new(&window, Layers::new(self.layers_bounds), self.samples).block_on();
         let mut tessellator = Tessellator::with_capacity_to_fit(&graphics.layers);
+        let mut capture = Capture::new();
+        let mut journal =
+            Journal::new(self.input_record, self.input_replay, size).unwrap_or_else(|error| {
+                log::error!("failed to open input journal: {}", error);
+                Journal::Off
+            });
+        let timestep = self.timestep;
+        let mut pan_zoom = self.pan_zoom;
         window.set_visible(true);
         event_loop.run(move |event, _, control_flow| match event {
             Event::WindowEvent { event, window_id } if window_id == window.id() => {
                 if let Some(new_size) = screen.try_process(&event) {
                     size = new_size;
+                    journal.record(tick, &event);
                     window.request_redraw();
                 } else if event == WindowEvent::CloseRequested {
                     *control_flow = ControlFlow::Exit;
-                } else if !mouse.try_process(&event, screen.half()) {
-                    keys.try_process(&event);
+                } else if !journal.is_replaying()
+                    && (mouse.try_process(&event, screen.half()) || keys.try_process(&event))
+                {
+                    journal.record(tick, &event);
                 }
             }
             Event::MainEventsCleared => window.request_redraw(),
             Event::RedrawRequested(window_id) if window_id == window.id() => {
                 let now = Instant::now();
-                delta = now - replace(&mut frame, now);
-                f(State {
-                    layers: &mut graphics.layers,
-                    tessellator: &mut tessellator,
-                    screen: &mut screen,
-                    mouse: &mouse,
-                    keys: &keys,
-                    delta: &delta,
-                });
-                if graphics
-                    .render(size, screen.zoom(), screen.clear_color())
-                    .is_err()
-                {
-                    *control_flow = ControlFlow::Exit;
+                let elapsed = now - replace(&mut frame, now);
+                let elapsed = match screen.recording_fps() {
+                    Some(fps) => Duration::from_secs(1) / fps,
+                    None => elapsed,
+                };
+                let (steps, alpha) = if let Some(step) = timestep {
+                    accumulator = (accumulator + elapsed).min(step * 8);
+                    let steps = (accumulator.as_nanos() / step.as_nanos()) as u32;
+                    accumulator -= step * steps;
+                    delta = step;
+                    (steps, accumulator.as_secs_f32() / step.as_secs_f32())
+                } else {
+                    delta = elapsed;
+                    (1, 0.0)
+                };
+                for _ in 0..steps {
+                    if let Some(size) = journal.replay(tick, &mut mouse, &mut keys, &mut screen) {
+                        window.set_inner_size(size);
+                    }
+                    graphics.layers.clear_overflows();
+                    if let Some(pan_zoom) = &mut pan_zoom {
+                        pan_zoom.update(&mut screen, &mouse, &keys, delta);
+                    }
+                    f(State {
+                        layers: &mut graphics.layers,
+                        tessellator: &mut tessellator,
+                        screen: &mut screen,
+                        mouse: &mouse,
+                        keys: &keys,
+                        delta: &delta,
+                        alpha,
+                    });
+                    tick += 1;
+                    graphics.layers.unset();
+                    mouse.unset();
+                    keys.unset();
+                    screen.unset();
+                }
+                match graphics.render(
+                    size,
+                    screen.camera(),
+                    screen.clear_color(),
+                    screen.is_capturing(),
+                ) {
+                    Ok(rgba) => capture.process(&mut screen, size.width, size.height, rgba),
+                    Err(()) => *control_flow = ControlFlow::Exit,
                 }
-                mouse.unset();
-                keys.unset();
-                screen.unset();
+            }
+            Event::LoopDestroyed => {
+                capture.finish();
+                journal.flush();
             }
             _ => {}
         })
@@ -0,0 +1,277 @@
+use {
+    crate::{
+        camera::Camera,
+        input::{Keys, Mouse, MouseButton, VirtualKeyCode},
+        screen::Screen,
+    },
+    glam::Vec2,
+    std::time::Duration,
+};
+
+const SETTLED: f32 = 0.001;
+
+const MIN_GLIDE: f32 = 1.0;
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct PanZoom {
+    pub button: MouseButton,
+    pub zoom_step: f32,
+    pub min_zoom: f32,
+    pub max_zoom: f32,
+    pub damping: f32,
+    pub reset_key: Option<VirtualKeyCode>,
+    pub fit: Option<(Vec2, Vec2)>,
+    zoom: Option<(f32, Vec2)>,
+    glide: Vec2,
+    resetting: bool,
+}
+
+impl Default for PanZoom {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PanZoom {
+    pub fn new() -> Self {
+        Self {
+            button: Mouse::MIDDLE,
+            zoom_step: 1.1,
+            min_zoom: 0.01,
+            max_zoom: 100.0,
+            damping: 0.08,
+            reset_key: Some(VirtualKeyCode::Home),
+            fit: None,
+            zoom: None,
+            glide: Vec2::ZERO,
+            resetting: false,
+        }
+    }
+
+    pub fn with_button(self, button: MouseButton) -> Self {
+        Self { button, ..self }
+    }
+
+    pub fn with_zoom_step(self, zoom_step: f32) -> Self {
+        Self { zoom_step, ..self }
+    }
+
+    pub fn with_zoom_limits(self, min_zoom: f32, max_zoom: f32) -> Self {
+        Self {
+            min_zoom,
+            max_zoom,
+            ..self
+        }
+    }
+
+    pub fn with_damping(self, damping: f32) -> Self {
+        Self { damping, ..self }
+    }
+
+    pub fn with_reset_key(self, reset_key: Option<VirtualKeyCode>) -> Self {
+        Self { reset_key, ..self }
+    }
+
+    pub fn with_fit(self, min: Vec2, max: Vec2) -> Self {
+        Self {
+            fit: Some((min, max)),
+            ..self
+        }
+    }
+
+    pub fn is_settled(&self) -> bool {
+        self.zoom.is_none() && !self.resetting && self.glide == Vec2::ZERO
+    }
+
+    pub fn home(&self, screen_size: Vec2) -> Camera {
+        match self.fit {
+            Some((min, max)) => {
+                let zoom = ((max - min) / screen_size).max_element();
+                Camera::default()
+                    .with_translation((min + max) * 0.5)
+                    .with_zoom(self.clamp(zoom))
+            }
+            None => Camera::default(),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.zoom = None;
+        self.glide = Vec2::ZERO;
+        self.resetting = true;
+    }
+
+    pub fn update(&mut self, screen: &mut Screen, mouse: &Mouse, keys: &Keys, delta: Duration) {
+        let delta = delta.as_secs_f32();
+        let t = if self.damping > 0.0 {
+            1.0 - (-delta / self.damping).exp()
+        } else {
+            1.0
+        };
+        if matches!(self.reset_key, Some(key) if keys.is_just_pressed(key)) {
+            self.reset();
+        }
+        let home = self.home(screen.size());
+        let camera = screen.camera_mut();
+
+        if mouse.is_pressed(self.button) {
+            camera.pan(mouse.velocity);
+            if delta > 0.0 {
+                self.glide = self.glide.lerp(mouse.velocity / delta, t);
+            }
+            self.resetting = false;
+        } else if self.glide != Vec2::ZERO {
+            camera.pan(self.glide * delta);
+            self.glide *= 1.0 - t;
+            if self.glide.length() < MIN_GLIDE {
+                self.glide = Vec2::ZERO;
+            }
+        }
+
+        if mouse.scroll.y != 0.0 {
+            let from = self.zoom.map_or(camera.zoom, |(zoom, _)| zoom);
+            let zoom = self.clamp(from / self.zoom_step.powf(mouse.scroll.y));
+            self.zoom = Some((zoom, mouse.position));
+            self.resetting = false;
+        }
+        if let Some((zoom, focus)) = self.zoom {
+            let next = camera.zoom * (zoom / camera.zoom).powf(t);
+            if (next / zoom - 1.0).abs() < SETTLED {
+                camera.zoom_about(zoom, focus);
+                self.zoom = None;
+            } else {
+                camera.zoom_about(next, focus);
+            }
+        }
+
+        if self.resetting {
+            let next = Camera {
+                translation: camera.translation.lerp(home.translation, t),
+                rotation: camera.rotation + (home.rotation - camera.rotation) * t,
+                zoom: camera.zoom * (home.zoom / camera.zoom).powf(t),
+            };
+            let offset = (next.translation - home.translation).length() / home.zoom;
+            if offset < SETTLED
+                && (next.rotation - home.rotation).abs() < SETTLED
+                && (next.zoom / home.zoom - 1.0).abs() < SETTLED
+            {
+                *camera = home;
+                self.resetting = false;
+            } else {
+                *camera = next;
+            }
+        }
+    }
+
+    fn clamp(&self, zoom: f32) -> f32 {
+        zoom.max(self.min_zoom).min(self.max_zoom)
+    }
+}
+
+pub fn pan_zoom() -> PanZoom {
+    PanZoom::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::camera::camera, glam::vec2, winit::dpi::PhysicalSize};
+
+    const FRAME: Duration = Duration::from_nanos(16_666_667);
+
+    struct Input {
+        screen: Screen,
+        mouse: Mouse,
+        keys: Keys,
+    }
+
+    impl Input {
+        fn new() -> Self {
+            Self {
+                screen: Screen::new(PhysicalSize::new(100, 100)),
+                mouse: Mouse::new(),
+                keys: Keys::new(),
+            }
+        }
+
+        fn update(&mut self, pan_zoom: &mut PanZoom) -> Camera {
+            pan_zoom.update(&mut self.screen, &self.mouse, &self.keys, FRAME);
+            self.mouse.unset();
+            self.keys.unset();
+            *self.screen.camera()
+        }
+    }
+
+    #[test]
+    fn zoom_clamps_to_limits() {
+        let mut pan_zoom = pan_zoom().with_zoom_limits(0.5, 2.0).with_damping(0.0);
+        let mut input = Input::new();
+        input.mouse.scroll_by(vec2(0.0, 100.0));
+        assert_eq!(input.update(&mut pan_zoom).zoom, 0.5);
+        input.mouse.scroll_by(vec2(0.0, -100.0));
+        assert_eq!(input.update(&mut pan_zoom).zoom, 2.0);
+        assert!(pan_zoom.is_settled());
+    }
+
+    #[test]
+    fn zoom_eases_towards_target() {
+        let mut pan_zoom = pan_zoom();
+        let mut input = Input::new();
+        input.mouse.scroll_by(vec2(0.0, -1.0));
+        let mut zooms = vec![input.update(&mut pan_zoom).zoom];
+        while !pan_zoom.is_settled() {
+            zooms.push(input.update(&mut pan_zoom).zoom);
+            assert!(zooms.len() < 200);
+        }
+        assert!(zooms.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!((zooms.last().unwrap() - 1.1).abs() < 1e-6);
+    }
+
+    #[test]
+    fn glide_decays() {
+        let mut pan_zoom = pan_zoom();
+        let mut input = Input::new();
+        input.mouse.press(Mouse::MIDDLE);
+        input.mouse.move_to(vec2(10.0, 0.0));
+        let mut x = input.update(&mut pan_zoom).translation.x;
+        assert_eq!(x, -10.0);
+        input.mouse.release(Mouse::MIDDLE);
+        x = input.update(&mut pan_zoom).translation.x;
+        let mut steps = Vec::new();
+        while !pan_zoom.is_settled() {
+            let next = input.update(&mut pan_zoom).translation.x;
+            steps.push(x - next);
+            x = next;
+            assert!(steps.len() < 200);
+        }
+        assert!(steps.len() > 1);
+        assert!(steps
+            .windows(2)
+            .all(|pair| pair[0] > pair[1] && pair[1] > 0.0));
+    }
+
+    #[test]
+    fn home_key_resets() {
+        let mut pan_zoom = pan_zoom();
+        let mut input = Input::new();
+        input.screen.set_camera(camera(vec2(50.0, -20.0), 0.5, 3.0));
+        input.update(&mut pan_zoom);
+        input.keys.press(VirtualKeyCode::Home);
+        let first = input.update(&mut pan_zoom);
+        assert_ne!(first, Camera::default());
+        for _ in 0..200 {
+            input.update(&mut pan_zoom);
+        }
+        assert!(pan_zoom.is_settled());
+        assert_eq!(*input.screen.camera(), Camera::default());
+    }
+
+    #[test]
+    fn home_fits_bounds() {
+        let pan_zoom = pan_zoom().with_fit(vec2(0.0, 0.0), vec2(400.0, 200.0));
+        let home = pan_zoom.home(vec2(100.0, 100.0));
+        assert_eq!(home.translation, vec2(200.0, 100.0));
+        assert_eq!(home.zoom, 4.0);
+        let pan_zoom = pan_zoom.with_zoom_limits(0.01, 2.0);
+        assert_eq!(pan_zoom.home(vec2(100.0, 100.0)).zoom, 2.0);
+    }
+}
@@ -0,0 +1,396 @@
+use {
+    crate::{
+        atlas::Atlas,
+        camera::Camera,
+        graphics::sample_count,
+        layers::{BlendMode, Color, Composite, Draw, Index, Layers},
+    },
+    glam::{vec2, Affine2, Vec2},
+    std::mem::{swap, take},
+};
+
+const SAMPLE_POSITIONS: [&[(f32, f32)]; 2] = [
+    &[(0.5, 0.5)],
+    &[
+        (0.375, 0.125),
+        (0.875, 0.375),
+        (0.125, 0.625),
+        (0.625, 0.875),
+    ],
+];
+
+pub struct Raster {
+    width: u32,
+    height: u32,
+    samples: &'static [(f32, f32)],
+    pixels: Vec<[f32; 4]>,
+    resolved: Vec<[f32; 4]>,
+    draws: Vec<Draw>,
+}
+
+#[derive(Copy, Clone)]
+struct Corner {
+    position: Vec2,
+    uv: Vec2,
+    color: [f32; 4],
+}
+
+impl Default for Raster {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Raster {
+    pub fn new() -> Self {
+        Self::with_samples(1)
+    }
+
+    pub fn with_samples(samples: u32) -> Self {
+        Self {
+            width: 0,
+            height: 0,
+            samples: SAMPLE_POSITIONS[(sample_count(samples) > 1) as usize],
+            pixels: Vec::new(),
+            resolved: Vec::new(),
+            draws: Vec::new(),
+        }
+    }
+
+    pub fn samples(&self) -> u32 {
+        self.samples.len() as _
+    }
+
+    pub fn render(
+        &mut self,
+        layers: &Layers,
+        width: u32,
+        height: u32,
+        camera: &Camera,
+        clear_color: Color,
+    ) -> Vec<u8> {
+        self.draw(layers, width, height, camera, clear_color);
+        self.rgba()
+    }
+
+    pub(crate) fn rgba(&self) -> Vec<u8> {
+        self.resolved
+            .iter()
+            .flat_map(|&[r, g, b, a]| {
+                [
+                    encode_srgb(r),
+                    encode_srgb(g),
+                    encode_srgb(b),
+                    encode_unorm(a),
+                ]
+            })
+            .collect()
+    }
+
+    pub(crate) fn render_xrgb(
+        &mut self,
+        layers: &Layers,
+        width: u32,
+        height: u32,
+        camera: &Camera,
+        clear_color: Color,
+        xrgb: &mut Vec<u32>,
+    ) {
+        self.draw(layers, width, height, camera, clear_color);
+        xrgb.clear();
+        xrgb.extend(self.resolved.iter().map(|&[r, g, b, _]| {
+            (encode_srgb(r) as u32) << 16 | (encode_srgb(g) as u32) << 8 | encode_srgb(b) as u32
+        }));
+    }
+
+    fn draw(
+        &mut self,
+        layers: &Layers,
+        width: u32,
+        height: u32,
+        camera: &Camera,
+        clear_color: Color,
+    ) {
+        self.width = width.max(1);
+        self.height = height.max(1);
+        self.pixels.clear();
+        self.pixels.resize(
+            (self.width * self.height) as usize * self.samples.len(),
+            unpack(clear_color),
+        );
+        self.draw_layers(layers, camera);
+        self.resolve();
+    }
+
+    fn resolve(&mut self) {
+        let scale = (self.samples.len() as f32).recip();
+        self.resolved.clear();
+        self.resolved
+            .extend(self.pixels.chunks_exact(self.samples.len()).map(|samples| {
+                let mut sum = [0.0; 4];
+                for sample in samples {
+                    for i in 0..4 {
+                        sum[i] += sample[i];
+                    }
+                }
+                sum.map(|channel| channel * scale)
+            }));
+    }
+
+    fn draw_layers(&mut self, layers: &Layers, camera: &Camera) {
+        let half = vec2(self.width as _, self.height as _) * 0.5;
+        let mut draws = take(&mut self.draws);
+        layers.sort_draws(&mut draws);
+        let views = draws
+            .iter()
+            .filter_map(|draw| Some((draw, layers.views[draw.chunk].view(camera)?)));
+        for (draw, view) in views {
+            let range = layers.ranges[draw.chunk];
+            let indices = &layers.indices[range.index_range()];
+            let instances = draw.instances.start as usize..draw.instances.end as usize;
+            for instance in &layers.instances[instances] {
+                let transform = view * Affine2::from_cols_array(&instance.transform);
+                let color = unpack(instance.color);
+                let to_pixel = |index: Index| {
+                    let vertex = *layers.vertices.get(index as usize)?;
+                    let uv = *layers.uvs.get(index as usize)?;
+                    let mut local = transform.transform_point2(vertex);
+                    let normal = layers
+                        .attributes
+                        .get(index as usize)
+                        .map_or(Vec2::ZERO, |attributes| attributes.normal);
+                    if let Some(direction) = transform.transform_vector2(normal).try_normalize() {
+                        local += direction * normal.length() * instance.extrude;
+                    }
+                    Some(Corner {
+                        position: vec2(half.x + local.x, half.y - local.y),
+                        uv: instance.region.uv(uv),
+                        color: unpack(
+                            layers
+                                .colors
+                                .get(index as usize)
+                                .copied()
+                                .unwrap_or(Color::WHITE),
+                        ),
+                    })
+                };
+                for triangle in indices.chunks_exact(3) {
+                    let corners = [triangle[0], triangle[1], triangle[2]].map(to_pixel);
+                    if let [Some(a), Some(b), Some(c)] = corners {
+                        self.fill_triangle(
+                            [a, b, c],
+                            color,
+                            layers.composites[draw.chunk],
+                            &layers.atlas,
+                        );
+                    }
+                }
+            }
+        }
+        self.draws = draws;
+    }
+
+    fn fill_triangle(
+        &mut self,
+        [corner_a, mut corner_b, mut corner_c]: [Corner; 3],
+        color: [f32; 4],
+        composite: Composite,
+        atlas: &Atlas,
+    ) {
+        let mut area = edge(corner_a.position, corner_b.position, corner_c.position);
+        if area == 0.0 || !area.is_finite() {
+            return;
+        }
+        if area < 0.0 {
+            swap(&mut corner_b, &mut corner_c);
+            area = -area;
+        }
+        let [a, b, c] = [corner_a, corner_b, corner_c].map(|corner| corner.position);
+        let min = a.min(b).min(c).floor().max(Vec2::ZERO);
+        let max = a
+            .max(b)
+            .max(c)
+            .ceil()
+            .min(vec2(self.width as _, self.height as _));
+        let edges = [(b, c), (c, a), (a, b)];
+        let top_left = edges.map(|(from, to)| is_top_left(from, to));
+        for y in min.y as u32..max.y as u32 {
+            for x in min.x as u32..max.x as u32 {
+                let pixel = (y * self.width + x) as usize * self.samples.len();
+                let mut covered = 0u32;
+                for (i, &(dx, dy)) in self.samples.iter().enumerate() {
+                    let p = vec2(x as f32 + dx, y as f32 + dy);
+                    let is_covered = edges.iter().zip(top_left).all(|(&(from, to), top_left)| {
+                        let w = edge(from, to, p);
+                        w > 0.0 || (w == 0.0 && top_left)
+                    });
+                    if is_covered {
+                        covered |= 1 << i;
+                    }
+                }
+                if covered != 0 {
+                    let p = vec2(x as f32 + 0.5, y as f32 + 0.5);
+                    let weights = edges.map(|(from, to)| edge(from, to, p));
+                    let uv = (corner_a.uv * weights[0]
+                        + corner_b.uv * weights[1]
+                        + corner_c.uv * weights[2])
+                        / area;
+                    let texel = sample(atlas, uv);
+                    let mut src = color;
+                    for i in 0..4 {
+                        let vertex = (corner_a.color[i] * weights[0]
+                            + corner_b.color[i] * weights[1]
+                            + corner_c.color[i] * weights[2])
+                            / area;
+                        src[i] *= vertex * texel[i];
+                    }
+                    let src = premultiply(src, composite);
+                    for i in 0..self.samples.len() {
+                        if covered & 1 << i != 0 {
+                            blend(&mut self.pixels[pixel + i], src, composite.blend);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn edge(from: Vec2, to: Vec2, p: Vec2) -> f32 {
+    (to - from).perp_dot(p - from)
+}
+
+fn is_top_left(from: Vec2, to: Vec2) -> bool {
+    let d = to - from;
+    (d.y == 0.0 && d.x > 0.0) || d.y < 0.0
+}
+
+fn premultiply([r, g, b, a]: [f32; 4], composite: Composite) -> [f32; 4] {
+    let src = if composite.blend == BlendMode::Premultiplied {
+        [r, g, b, a]
+    } else {
+        [r * a, g * a, b * a, a]
+    };
+    src.map(|channel| channel * composite.opacity)
+}
+
+fn blend(dst: &mut [f32; 4], src: [f32; 4], mode: BlendMode) {
+    let inv = 1.0 - src[3];
+    for i in 0..3 {
+        let blended = match mode {
+            BlendMode::Alpha | BlendMode::Premultiplied => src[i] + dst[i] * inv,
+            BlendMode::Additive => src[i] + dst[i],
+            BlendMode::Multiply => src[i] * dst[i] + dst[i] * inv,
+            BlendMode::Screen => src[i] + dst[i] * (1.0 - src[i]),
+        };
+        dst[i] = blended.clamp(0.0, 1.0);
+    }
+    dst[3] = (src[3] + dst[3] * inv).clamp(0.0, 1.0);
+}
+
+fn sample(atlas: &Atlas, uv: Vec2) -> [f32; 4] {
+    let max = vec2(atlas.width as f32 - 1.0, atlas.height as f32 - 1.0);
+    let p = (uv * vec2(atlas.width as _, atlas.height as _) - 0.5).clamp(Vec2::ZERO, max);
+    let (x0, y0) = (p.x as u32, p.y as u32);
+    let (x1, y1) = ((x0 + 1).min(max.x as _), (y0 + 1).min(max.y as _));
+    let (fx, fy) = (p.x.fract(), p.y.fract());
+    let texel = |x, y| decode(atlas.texel(x, y));
+    let [t00, t10, t01, t11] = [texel(x0, y0), texel(x1, y0), texel(x0, y1), texel(x1, y1)];
+    let mut out = [0.0; 4];
+    for i in 0..4 {
+        let top = t00[i] + (t10[i] - t00[i]) * fx;
+        let bottom = t01[i] + (t11[i] - t01[i]) * fx;
+        out[i] = top + (bottom - top) * fy;
+    }
+    out
+}
+
+fn decode([r, g, b, a]: [u8; 4]) -> [f32; 4] {
+    [
+        decode_srgb(r),
+        decode_srgb(g),
+        decode_srgb(b),
+        a as f32 / 255.0,
+    ]
+}
+
+fn decode_srgb(encoded: u8) -> f32 {
+    let encoded = encoded as f32 / 255.0;
+    if encoded <= 0.04045 {
+        encoded / 12.92
+    } else {
+        ((encoded + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn unpack(color: Color) -> [f32; 4] {
+    [
+        color.r as f32 / 255.0,
+        color.g as f32 / 255.0,
+        color.b as f32 / 255.0,
+        color.a as f32 / 255.0,
+    ]
+}
+
+fn encode_unorm(value: f32) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+fn encode_srgb(linear: f32) -> u8 {
+    let linear = linear.clamp(0.0, 1.0);
+    encode_unorm(if linear <= 0.0031308 {
+        linear * 12.92
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::layers::{instance, layer_bounds},
+    };
+
+    #[test]
+    fn skips_invalid_indices() {
+        let mut layers = Layers::new(&[layer_bounds(3, 6, 1)]);
+        let mut layer = layers.get_mut(0).unwrap();
+        layer
+            .set_vertices([vec2(-10.0, -10.0), vec2(0.0, 10.0), vec2(10.0, -10.0)])
+            .unwrap();
+        layer.set_indices([0, 1, 2, 0, 1, 7]).unwrap();
+        layer
+            .set_instances([instance(Affine2::IDENTITY, Color::WHITE)])
+            .unwrap();
+        let rgba = Raster::new().render(&layers, 4, 4, &Camera::default(), Color::default());
+        assert_eq!(rgba[(2 * 4 + 2) * 4..][..4], [255; 4]);
+    }
+
+    #[test]
+    fn samples_cover_edges() {
+        let mut layers = Layers::new(&[layer_bounds(4, 6, 1)]);
+        let mut layer = layers.get_mut(0).unwrap();
+        layer
+            .set_vertices([
+                vec2(-10.0, -10.0),
+                vec2(-10.0, 10.0),
+                vec2(0.25, 10.0),
+                vec2(0.25, -10.0),
+            ])
+            .unwrap();
+        layer.set_indices([0, 1, 2, 0, 2, 3]).unwrap();
+        layer
+            .set_instances([instance(Affine2::IDENTITY, Color::WHITE)])
+            .unwrap();
+        let alphas = |samples| {
+            let mut raster = Raster::with_samples(samples);
+            let rgba = raster.render(&layers, 4, 4, &Camera::default(), Color::default());
+            [1, 2].map(|x| rgba[(4 + x) * 4 + 3])
+        };
+        assert_eq!(alphas(1), [255, 0]);
+        assert_eq!(alphas(4), [255, 64]);
+        assert_eq!(Raster::with_samples(2).samples(), 4);
+        assert_eq!(Raster::with_samples(8).samples(), 4);
+    }
+}
@@ -0,0 +1,377 @@
+use {
+    crate::{
+        input::{
+            Keys, ModifiersState, Mouse, MouseButton, MouseScrollDelta, VirtualKeyCode, WindowEvent,
+        },
+        screen::Screen,
+    },
+    std::{
+        collections::VecDeque,
+        fs::File,
+        io::{self, BufRead, BufReader, BufWriter, Write},
+        mem::transmute,
+        path::Path,
+    },
+    winit::{
+        dpi::{PhysicalPosition, PhysicalSize},
+        event::{DeviceId, ElementState, KeyboardInput, TouchPhase},
+    },
+};
+
+const KEY_CODES: u32 = VirtualKeyCode::Cut as u32 + 1;
+
+pub(crate) enum Journal {
+    Off,
+    Record(BufWriter<File>),
+    Replay(
+        Option<PhysicalSize<u32>>,
+        VecDeque<(u64, WindowEvent<'static>)>,
+    ),
+}
+
+impl Journal {
+    pub fn new(
+        record: Option<&Path>,
+        replay: Option<&Path>,
+        size: PhysicalSize<u32>,
+    ) -> io::Result<Self> {
+        if record.is_some() && replay.is_some() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "cannot record and replay input at the same time",
+            ));
+        }
+        if let Some(path) = replay {
+            let mut size = None;
+            let mut events = VecDeque::new();
+            for (number, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let invalid = |what| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!(
+                            "{}:{}: invalid {} {:?}",
+                            path.display(),
+                            number + 1,
+                            what,
+                            line
+                        ),
+                    )
+                };
+                if size.is_none() {
+                    size = Some(parse_size(&line).ok_or_else(|| invalid("header"))?);
+                } else {
+                    events.push_back(parse(&line).ok_or_else(|| invalid("event"))?);
+                }
+            }
+            Ok(Self::Replay(size, events))
+        } else if let Some(path) = record {
+            let mut file = BufWriter::new(File::create(path)?);
+            writeln!(file, "size {} {}", size.width, size.height)?;
+            Ok(Self::Record(file))
+        } else {
+            Ok(Self::Off)
+        }
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        matches!(self, Self::Replay(..))
+    }
+
+    pub fn record(&mut self, tick: u64, event: &WindowEvent) {
+        if let Self::Record(file) = self {
+            if let Some(line) = format(event) {
+                if let Err(error) = writeln!(file, "{} {}", tick, line) {
+                    log::error!("failed to record input: {}", error);
+                    *self = Self::Off;
+                }
+            }
+        }
+    }
+
+    pub fn replay(
+        &mut self,
+        tick: u64,
+        mouse: &mut Mouse,
+        keys: &mut Keys,
+        screen: &mut Screen,
+    ) -> Option<PhysicalSize<u32>> {
+        if let Self::Replay(header, events) = self {
+            let mut resized = header.take();
+            if let Some(size) = resized {
+                screen.try_process(&WindowEvent::Resized(size));
+            }
+            while let Some((_, event)) = events.front().filter(|(at, _)| *at <= tick) {
+                if let Some(size) = screen.try_process(event) {
+                    resized = Some(size);
+                } else if !mouse.try_process(event, screen.half()) {
+                    keys.try_process(event);
+                }
+                events.pop_front();
+            }
+            if events.is_empty() {
+                *self = Self::Off;
+            }
+            resized
+        } else {
+            None
+        }
+    }
+
+    pub fn flush(&mut self) {
+        if let Self::Record(file) = self {
+            if let Err(error) = file.flush() {
+                log::error!("failed to record input: {}", error);
+            }
+        }
+    }
+}
+
+fn format(event: &WindowEvent) -> Option<String> {
+    Some(match event {
+        WindowEvent::CursorEntered { .. } => "entered".into(),
+        WindowEvent::CursorLeft { .. } => "left".into(),
+        WindowEvent::CursorMoved { position, .. } => format!("moved {} {}", position.x, position.y),
+        WindowEvent::MouseInput { state, button, .. } => {
+            let button = match button {
+                MouseButton::Left => "left".into(),
+                MouseButton::Right => "right".into(),
+                MouseButton::Middle => "middle".into(),
+                MouseButton::Other(other) => other.to_string(),
+            };
+            format!("button {} {}", format_state(*state), button)
+        }
+        WindowEvent::MouseWheel { delta, .. } => match delta {
+            MouseScrollDelta::LineDelta(x, y) => format!("wheel line {} {}", x, y),
+            MouseScrollDelta::PixelDelta(xy) => format!("wheel pixel {} {}", xy.x, xy.y),
+        },
+        WindowEvent::KeyboardInput { input, .. } => format!(
+            "key {} {} {}",
+            format_state(input.state),
+            input.scancode,
+            input
+                .virtual_keycode
+                .map_or_else(|| "-".into(), |key| (key as u32).to_string())
+        ),
+        WindowEvent::ModifiersChanged(state) => format!("modifiers {}", state.bits()),
+        WindowEvent::Resized(size) => format!("resized {} {}", size.width, size.height),
+        WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+            format!("resized {} {}", new_inner_size.width, new_inner_size.height)
+        }
+        _ => return None,
+    })
+}
+
+fn format_state(state: ElementState) -> &'static str {
+    match state {
+        ElementState::Pressed => "pressed",
+        ElementState::Released => "released",
+    }
+}
+
+#[allow(deprecated)]
+fn parse(line: &str) -> Option<(u64, WindowEvent<'static>)> {
+    let device_id = unsafe { DeviceId::dummy() };
+    let mut words = line.split_whitespace();
+    let tick = words.next()?.parse().ok()?;
+    let mut next = || words.next();
+    let event = match next()? {
+        "entered" => WindowEvent::CursorEntered { device_id },
+        "left" => WindowEvent::CursorLeft { device_id },
+        "moved" => WindowEvent::CursorMoved {
+            device_id,
+            position: PhysicalPosition::new(next()?.parse().ok()?, next()?.parse().ok()?),
+            modifiers: ModifiersState::empty(),
+        },
+        "button" => WindowEvent::MouseInput {
+            device_id,
+            state: parse_state(next()?)?,
+            button: match next()? {
+                "left" => MouseButton::Left,
+                "right" => MouseButton::Right,
+                "middle" => MouseButton::Middle,
+                other => MouseButton::Other(other.parse().ok()?),
+            },
+            modifiers: ModifiersState::empty(),
+        },
+        "wheel" => WindowEvent::MouseWheel {
+            device_id,
+            delta: match next()? {
+                "line" => MouseScrollDelta::LineDelta(next()?.parse().ok()?, next()?.parse().ok()?),
+                "pixel" => MouseScrollDelta::PixelDelta(PhysicalPosition::new(
+                    next()?.parse().ok()?,
+                    next()?.parse().ok()?,
+                )),
+                _ => return None,
+            },
+            phase: TouchPhase::Moved,
+            modifiers: ModifiersState::empty(),
+        },
+        "key" => WindowEvent::KeyboardInput {
+            device_id,
+            input: KeyboardInput {
+                state: parse_state(next()?)?,
+                scancode: next()?.parse().ok()?,
+                virtual_keycode: match next()? {
+                    "-" => None,
+                    key => match key.parse().ok()? {
+                        key if key < KEY_CODES => {
+                            Some(unsafe { transmute::<u32, VirtualKeyCode>(key) })
+                        }
+                        _ => return None,
+                    },
+                },
+                modifiers: ModifiersState::empty(),
+            },
+            is_synthetic: false,
+        },
+        "modifiers" => {
+            WindowEvent::ModifiersChanged(ModifiersState::from_bits(next()?.parse().ok()?)?)
+        }
+        "resized" => WindowEvent::Resized(PhysicalSize::new(
+            next()?.parse().ok()?,
+            next()?.parse().ok()?,
+        )),
+        _ => return None,
+    };
+    Some((tick, event))
+}
+
+fn parse_size(line: &str) -> Option<PhysicalSize<u32>> {
+    match line.split_whitespace().collect::<Vec<_>>()[..] {
+        ["size", width, height] => {
+            Some(PhysicalSize::new(width.parse().ok()?, height.parse().ok()?))
+        }
+        _ => None,
+    }
+}
+
+fn parse_state(word: &str) -> Option<ElementState> {
+    match word {
+        "pressed" => Some(ElementState::Pressed),
+        "released" => Some(ElementState::Released),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, glam::Vec2, std::fs};
+
+    #[test]
+    #[allow(deprecated)]
+    fn round_trip() {
+        let device_id = unsafe { DeviceId::dummy() };
+        let modifiers = ModifiersState::empty();
+        let events = [
+            WindowEvent::CursorEntered { device_id },
+            WindowEvent::CursorMoved {
+                device_id,
+                position: PhysicalPosition::new(12.5, -3.0),
+                modifiers,
+            },
+            WindowEvent::MouseInput {
+                device_id,
+                state: ElementState::Pressed,
+                button: MouseButton::Other(7),
+                modifiers,
+            },
+            WindowEvent::MouseWheel {
+                device_id,
+                delta: MouseScrollDelta::LineDelta(0.0, -2.0),
+                phase: TouchPhase::Moved,
+                modifiers,
+            },
+            WindowEvent::MouseWheel {
+                device_id,
+                delta: MouseScrollDelta::PixelDelta(PhysicalPosition::new(1.5, 4.0)),
+                phase: TouchPhase::Moved,
+                modifiers,
+            },
+            WindowEvent::KeyboardInput {
+                device_id,
+                input: KeyboardInput {
+                    state: ElementState::Released,
+                    scancode: 30,
+                    virtual_keycode: Some(VirtualKeyCode::A),
+                    modifiers,
+                },
+                is_synthetic: false,
+            },
+            WindowEvent::KeyboardInput {
+                device_id,
+                input: KeyboardInput {
+                    state: ElementState::Pressed,
+                    scancode: 1,
+                    virtual_keycode: None,
+                    modifiers,
+                },
+                is_synthetic: false,
+            },
+            WindowEvent::ModifiersChanged(ModifiersState::SHIFT | ModifiersState::CTRL),
+            WindowEvent::Resized(PhysicalSize::new(640, 480)),
+            WindowEvent::CursorLeft { device_id },
+        ];
+        for (tick, event) in events.into_iter().enumerate() {
+            let line = format!("{} {}", tick, format(&event).unwrap());
+            assert_eq!(parse(&line), Some((tick as u64, event)), "{}", line);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_lines() {
+        for line in [
+            "",
+            "1",
+            "x moved 1 2",
+            "1 moved 1",
+            "1 key pressed 1 99999",
+            "1 focus",
+            "1 resized 640",
+        ] {
+            assert_eq!(parse(line), None, "{}", line);
+        }
+        assert_eq!(
+            parse_size("size 640 480"),
+            Some(PhysicalSize::new(640, 480))
+        );
+        assert_eq!(parse_size("0 moved 1 2"), None);
+    }
+
+    #[test]
+    fn rejects_record_and_replay() {
+        let path = std::env::temp_dir().join("ketupa_rejects_record_and_replay.journal");
+        let size = PhysicalSize::new(640, 480);
+        let error = Journal::new(Some(&path), Some(&path), size).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn replays_window_size() {
+        let path = std::env::temp_dir().join("ketupa_replays_window_size.journal");
+        fs::write(
+            &path,
+            "size 200 100\n0 moved 100 50\n1 resized 400 300\n1 moved 100 50\n",
+        )
+        .unwrap();
+        let mut journal = Journal::new(None, Some(&path), PhysicalSize::new(1, 1)).unwrap();
+        fs::remove_file(&path).unwrap();
+        let (mut mouse, mut keys) = (Mouse::new(), Keys::new());
+        let mut screen = Screen::new(PhysicalSize::new(1000, 1000));
+
+        let resized = journal.replay(0, &mut mouse, &mut keys, &mut screen);
+        assert_eq!(resized, Some(PhysicalSize::new(200, 100)));
+        assert_eq!(screen.half(), Vec2::new(100.0, 50.0));
+        assert_eq!(mouse.position, Vec2::ZERO);
+
+        let resized = journal.replay(1, &mut mouse, &mut keys, &mut screen);
+        assert_eq!(resized, Some(PhysicalSize::new(400, 300)));
+        assert_eq!(screen.half(), Vec2::new(200.0, 150.0));
+        assert_eq!(mouse.position, Vec2::new(-100.0, 100.0));
+        assert!(!journal.is_replaying());
+    }
+}
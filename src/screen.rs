@@ -1,22 +1,27 @@
 use {
-    crate::layers::Color,
+    crate::{camera::Camera, layers::Color},
     glam::{vec2, Vec2},
+    std::path::PathBuf,
     winit::{dpi::PhysicalSize, event::WindowEvent},
 };
 
 pub struct Screen {
     size: Vec2,
     half: Vec2,
-    zoom: f32,
-    zoom_recip: f32,
+    camera: Camera,
     has_resized: bool,
     clear_color: Color,
+    capture: Option<PathBuf>,
+    recording: Option<(PathBuf, u32)>,
 }
 
 impl Screen {
     pub fn set_zoom(&mut self, zoom: f32) {
-        self.zoom = zoom;
-        self.zoom_recip = zoom.recip();
+        self.camera.zoom = zoom;
+    }
+
+    pub fn set_camera(&mut self, camera: Camera) {
+        self.camera = camera;
     }
 
     pub fn set_clear_color(&mut self, color: Color) {
@@ -32,19 +37,27 @@ impl Screen {
     }
 
     pub fn zoom(&self) -> f32 {
-        self.zoom
+        self.camera.zoom
     }
 
     pub fn zoom_recip(&self) -> f32 {
-        self.zoom_recip
+        self.camera.zoom.recip()
+    }
+
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
+
+    pub fn camera_mut(&mut self) -> &mut Camera {
+        &mut self.camera
     }
 
     pub fn world_of(&self, local: Vec2) -> Vec2 {
-        local * self.zoom
+        self.camera.world_of(local)
     }
 
     pub fn local_of(&self, world: Vec2) -> Vec2 {
-        world * self.zoom_recip
+        self.camera.local_of(world)
     }
 
     pub fn has_resized(&self) -> bool {
@@ -55,15 +68,36 @@ impl Screen {
         self.clear_color
     }
 
+    pub fn capture_frame(&mut self, path: impl Into<PathBuf>) {
+        self.capture = Some(path.into());
+    }
+
+    pub fn start_recording(&mut self, path: impl Into<PathBuf>, fps: u32) {
+        self.recording = Some((path.into(), fps.max(1)));
+    }
+
+    pub fn stop_recording(&mut self) {
+        self.recording = None;
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    pub fn recording_fps(&self) -> Option<u32> {
+        self.recording.as_ref().map(|(_, fps)| *fps)
+    }
+
     pub(crate) fn new(size: PhysicalSize<u32>) -> Self {
         let size = vec2(size.width as _, size.height as _);
         Self {
             size,
             half: size * 0.5,
-            zoom: 1.0,
-            zoom_recip: 1.0,
+            camera: Camera::default(),
             has_resized: true,
             clear_color: Color::new(0, 0, 0, 0),
+            capture: None,
+            recording: None,
         }
     }
 
@@ -83,6 +117,18 @@ impl Screen {
         }
     }
 
+    pub(crate) fn is_capturing(&self) -> bool {
+        self.capture.is_some() || self.recording.is_some()
+    }
+
+    pub(crate) fn take_capture(&mut self) -> Option<PathBuf> {
+        self.capture.take()
+    }
+
+    pub(crate) fn recording(&self) -> Option<&(PathBuf, u32)> {
+        self.recording.as_ref()
+    }
+
     pub(crate) fn unset(&mut self) {
         self.has_resized = false
     }
@@ -1,38 +1,89 @@
 struct ParamsUniform {
+    view: mat2x2<f32>;
+    view_translation: vec2<f32>;
     screen_half_recip: vec2<f32>;
-    screen_zoom: f32;
-    dummy: u32;
+    opacity: f32;
+    premultiply: f32;
 };
 
 [[group(0), binding(0)]]
 var<uniform> params: ParamsUniform;
 
+[[group(1), binding(0)]]
+var atlas: texture_2d<f32>;
+
+[[group(1), binding(1)]]
+var atlas_sampler: sampler;
+
 struct VertexInput {
     [[location(0)]] position: vec2<f32>;
     [[location(1)]] scale_and_rotation: vec4<f32>;
     [[location(2)]] translation_color: vec3<f32>;
+    [[location(3)]] region: vec4<f32>;
+    [[location(4)]] uv: vec2<f32>;
+    [[location(8)]] extrude: f32;
+};
+
+struct AttributesInput {
+    [[location(5)]] normal: vec2<f32>;
+};
+
+struct ColorInput {
+    [[location(9)]] vertex_color: vec4<f32>;
 };
 
 struct VertexOutput {
     [[builtin(position)]] clip_position: vec4<f32>;
     [[location(0)]] color: vec4<f32>;
+    [[location(1)]] uv: vec2<f32>;
 };
 
-[[stage(vertex)]]
-fn vs_main(in: VertexInput) -> VertexOutput {
+fn vertex(in: VertexInput, attributes: AttributesInput, vertex_color: vec4<f32>) -> VertexOutput {
     var out: VertexOutput;
-    out.clip_position.x = (dot(in.scale_and_rotation.xz, in.position)
-        + in.translation_color.x) 
-        * params.screen_half_recip.x;
-    out.clip_position.y = (dot(in.scale_and_rotation.yw, in.position)
-        + in.translation_color.y)
-        * params.screen_half_recip.y;
-    out.clip_position.w = params.screen_zoom;
-    out.color = unpack4x8unorm(bitcast<u32>(in.translation_color.z));
+    let transform = mat2x2<f32>(in.scale_and_rotation.xy, in.scale_and_rotation.zw);
+    let world = transform * in.position + in.translation_color.xy;
+    var local = params.view * world + params.view_translation;
+    let normal = params.view * (transform * attributes.normal);
+    if (in.extrude != 0.0 && dot(normal, normal) > 0.0) {
+        local = local + normalize(normal) * length(attributes.normal) * in.extrude;
+    }
+    out.clip_position = vec4<f32>(local * params.screen_half_recip, 0.0, 1.0);
+    out.color = unpack4x8unorm(bitcast<u32>(in.translation_color.z)) * vertex_color;
+    out.uv = in.region.xy + in.uv * in.region.zw;
     return out;
 }
 
+[[stage(vertex)]]
+fn vs_main(in: VertexInput) -> VertexOutput {
+    let attributes = AttributesInput(vec2<f32>(0.0));
+    return vertex(in, attributes, vec4<f32>(1.0));
+}
+
+[[stage(vertex)]]
+fn vs_attributes(in: VertexInput, attributes: AttributesInput) -> VertexOutput {
+    return vertex(in, attributes, vec4<f32>(1.0));
+}
+
+[[stage(vertex)]]
+fn vs_colors(in: VertexInput, color: ColorInput) -> VertexOutput {
+    let attributes = AttributesInput(vec2<f32>(0.0));
+    return vertex(in, attributes, color.vertex_color);
+}
+
+[[stage(vertex)]]
+fn vs_attributes_colors(
+    in: VertexInput,
+    attributes: AttributesInput,
+    color: ColorInput,
+) -> VertexOutput {
+    return vertex(in, attributes, color.vertex_color);
+}
+
 [[stage(fragment)]]
 fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
-    return in.color;
+    let color = in.color * textureSample(atlas, atlas_sampler, in.uv);
+    if (params.premultiply != 0.0) {
+        return vec4<f32>(color.rgb * color.a, color.a) * params.opacity;
+    }
+    return color * params.opacity;
 }
\ No newline at end of file
@@ -0,0 +1,249 @@
+use {
+    crate::{
+        headless::Headless,
+        input::{Keys, Mouse},
+        layers::{LayerBounds, Layers},
+        raster::Raster,
+        screen::Screen,
+        tesselator::Tessellator,
+        State,
+    },
+    std::{
+        env,
+        fs::{self, File},
+        io::{self, BufReader, BufWriter},
+        path::{Path, PathBuf},
+        time::Duration,
+    },
+    winit::dpi::PhysicalSize,
+};
+
+pub struct Script<'a> {
+    pub frame: usize,
+    pub mouse: &'a mut Mouse,
+    pub keys: &'a mut Keys,
+    pub delta: &'a mut Duration,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum Backend {
+    Auto,
+    Headless,
+    Raster,
+}
+
+pub struct Snapshot<'a> {
+    pub width: u32,
+    pub height: u32,
+    pub layers_bounds: &'a [LayerBounds],
+    pub frames: usize,
+    pub tolerance: u8,
+    pub samples: u32,
+    pub backend: Backend,
+}
+
+pub fn snapshot(
+    width: u32,
+    height: u32,
+    layers_bounds: &[LayerBounds],
+    frames: usize,
+) -> Snapshot<'_> {
+    Snapshot::new(width, height, layers_bounds, frames)
+}
+
+impl<'a> Snapshot<'a> {
+    pub const UPDATE_VAR: &'static str = "KETUPA_UPDATE_SNAPSHOTS";
+
+    pub fn new(width: u32, height: u32, layers_bounds: &'a [LayerBounds], frames: usize) -> Self {
+        Self {
+            width,
+            height,
+            layers_bounds,
+            frames,
+            tolerance: 0,
+            samples: 1,
+            backend: Backend::Raster,
+        }
+    }
+
+    pub fn with_tolerance(self, tolerance: u8) -> Self {
+        Self { tolerance, ..self }
+    }
+
+    pub fn with_samples(self, samples: u32) -> Self {
+        Self { samples, ..self }
+    }
+
+    pub fn with_backend(self, backend: Backend) -> Self {
+        Self { backend, ..self }
+    }
+
+    pub fn run(&self, mut script: impl FnMut(Script<'_>), mut f: impl FnMut(State<'_>)) -> Vec<u8> {
+        let mut layers = Layers::new(self.layers_bounds);
+        let mut tessellator = Tessellator::with_capacity_to_fit(&layers);
+        let mut screen = Screen::new(PhysicalSize::new(self.width, self.height));
+        let mut mouse = Mouse::new();
+        let mut keys = Keys::new();
+        for frame in 0..self.frames {
+            let mut delta = Duration::from_secs(1) / 60;
+            script(Script {
+                frame,
+                mouse: &mut mouse,
+                keys: &mut keys,
+                delta: &mut delta,
+            });
+            layers.clear_overflows();
+            f(State {
+                layers: &mut layers,
+                tessellator: &mut tessellator,
+                screen: &mut screen,
+                mouse: &mouse,
+                keys: &keys,
+                delta: &delta,
+                alpha: 0.0,
+            });
+            layers.unset();
+            mouse.unset();
+            keys.unset();
+            screen.unset();
+        }
+        let (camera, clear_color) = (screen.camera(), screen.clear_color());
+        let headless = match self.backend {
+            Backend::Raster => None,
+            Backend::Auto => {
+                let headless = Headless::with_samples(&layers, self.samples);
+                let backend = if headless.is_some() {
+                    "headless"
+                } else {
+                    "raster"
+                };
+                log::info!("snapshot rendered with the {} backend", backend);
+                headless
+            }
+            Backend::Headless => Some(
+                Headless::with_samples(&layers, self.samples)
+                    .expect("no wgpu adapter for the headless snapshot backend"),
+            ),
+        };
+        match headless {
+            Some(mut headless) => {
+                headless.render(&mut layers, self.width, self.height, camera, clear_color)
+            }
+            None => Raster::with_samples(self.samples).render(
+                &layers,
+                self.width,
+                self.height,
+                camera,
+                clear_color,
+            ),
+        }
+    }
+
+    pub fn assert_matches(
+        &self,
+        path: impl AsRef<Path>,
+        script: impl FnMut(Script<'_>),
+        f: impl FnMut(State<'_>),
+    ) {
+        let path = path.as_ref();
+        let actual = self.run(script, f);
+        if env::var_os(Self::UPDATE_VAR).is_some() {
+            self.save(path, &actual);
+            return;
+        }
+        if !path.exists() {
+            self.save(&sibling(path, "actual"), &actual);
+            panic!(
+                "snapshot {} is missing, set {} to create it",
+                path.display(),
+                Self::UPDATE_VAR
+            );
+        }
+        let (width, height, expected) = read_png(path)
+            .unwrap_or_else(|error| panic!("failed to read {}: {}", path.display(), error));
+        if (width, height) != (self.width, self.height) {
+            self.save(&sibling(path, "actual"), &actual);
+            panic!(
+                "snapshot {} is {}x{}, rendered {}x{}",
+                path.display(),
+                width,
+                height,
+                self.width,
+                self.height
+            );
+        }
+        let mut mismatches = 0;
+        let mut diff = Vec::with_capacity(actual.len());
+        for (a, e) in actual.chunks_exact(4).zip(expected.chunks_exact(4)) {
+            if a.iter()
+                .zip(e)
+                .any(|(a, e)| a.abs_diff(*e) > self.tolerance)
+            {
+                mismatches += 1;
+                diff.extend_from_slice(&[255, 0, 255, 255]);
+            } else {
+                let gray = ((e[0] as u32 + e[1] as u32 + e[2] as u32) / 12) as u8;
+                diff.extend_from_slice(&[gray, gray, gray, 255]);
+            }
+        }
+        if mismatches != 0 {
+            self.save(&sibling(path, "actual"), &actual);
+            self.save(&sibling(path, "diff"), &diff);
+            panic!(
+                "snapshot {} differs in {} pixels with tolerance {}",
+                path.display(),
+                mismatches,
+                self.tolerance
+            );
+        }
+    }
+
+    fn save(&self, path: &Path, rgba: &[u8]) {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .unwrap_or_else(|error| panic!("failed to create {}: {}", parent.display(), error));
+        }
+        write_png(path, self.width, self.height, rgba)
+            .unwrap_or_else(|error| panic!("failed to write {}: {}", path.display(), error));
+    }
+}
+
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!("{}.{}.png", stem, suffix))
+}
+
+pub(crate) fn write_png(
+    path: impl AsRef<Path>,
+    width: u32,
+    height: u32,
+    rgba: &[u8],
+) -> io::Result<()> {
+    let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(rgba)?;
+    Ok(())
+}
+
+pub(crate) fn read_png(path: impl AsRef<Path>) -> io::Result<(u32, u32, Vec<u8>)> {
+    let mut decoder = png::Decoder::new(BufReader::new(File::open(path)?));
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info()?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf)?;
+    buf.truncate(info.buffer_size());
+    let rgba = match info.color_type {
+        png::ColorType::Rgba => buf,
+        png::ColorType::Rgb => buf
+            .chunks_exact(3)
+            .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 255])
+            .collect(),
+        png::ColorType::GrayscaleAlpha => buf
+            .chunks_exact(2)
+            .flat_map(|ga| [ga[0], ga[0], ga[0], ga[1]])
+            .collect(),
+        _ => buf.into_iter().flat_map(|g| [g, g, g, 255]).collect(),
+    };
+    Ok((info.width, info.height, rgba))
+}
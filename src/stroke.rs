@@ -0,0 +1,280 @@
+use lyon_tessellation::{
+    math::Point,
+    path::{iterator::PathIterator, PathEvent},
+    LineCap, LineJoin, StrokeOptions,
+};
+
+const ROUND_TOLERANCE: f32 = 0.01;
+const MIN_DASH_TOLERANCES: f32 = 100.0;
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct StrokeStyle {
+    pub width: f32,
+    pub join: LineJoin,
+    pub cap: LineCap,
+    pub miter_limit: f32,
+    pub dashes: Vec<f32>,
+    pub dash_offset: f32,
+}
+
+impl StrokeStyle {
+    pub fn new(width: f32) -> Self {
+        Self {
+            width,
+            join: LineJoin::Bevel,
+            cap: LineCap::Butt,
+            miter_limit: StrokeOptions::DEFAULT_MITER_LIMIT,
+            dashes: Vec::new(),
+            dash_offset: 0.0,
+        }
+    }
+
+    pub fn with_width(self, width: f32) -> Self {
+        Self { width, ..self }
+    }
+
+    pub fn with_join(self, join: LineJoin) -> Self {
+        Self { join, ..self }
+    }
+
+    pub fn with_cap(self, cap: LineCap) -> Self {
+        Self { cap, ..self }
+    }
+
+    pub fn with_miter_limit(self, miter_limit: f32) -> Self {
+        Self {
+            miter_limit,
+            ..self
+        }
+    }
+
+    pub fn with_dashes(self, dashes: impl Into<Vec<f32>>) -> Self {
+        Self {
+            dashes: dashes.into(),
+            ..self
+        }
+    }
+
+    pub fn with_dash_offset(self, dash_offset: f32) -> Self {
+        Self {
+            dash_offset,
+            ..self
+        }
+    }
+
+    pub fn is_dashed(&self) -> bool {
+        self.dashes.iter().all(|&dash| dash >= 0.0) && self.dashes.iter().sum::<f32>() > 0.0
+    }
+
+    pub(crate) fn options(&self, tolerance: f32) -> StrokeOptions {
+        let tolerance = if self.join == LineJoin::Round || self.cap == LineCap::Round {
+            tolerance.max(self.width * ROUND_TOLERANCE)
+        } else {
+            tolerance
+        };
+        StrokeOptions::default()
+            .with_tolerance(tolerance)
+            .with_line_join(self.join)
+            .with_line_cap(self.cap)
+            .with_miter_limit(self.miter_limit.max(StrokeOptions::MINIMUM_MITER_LIMIT))
+            .with_line_width(self.width)
+    }
+
+    pub(crate) fn dash(
+        &self,
+        path: impl IntoIterator<Item = PathEvent>,
+        tolerance: f32,
+        events: &mut Vec<PathEvent>,
+    ) {
+        let mut dasher = Dasher {
+            events,
+            pattern: &self.dashes,
+            len: self.dashes.len() * (1 + self.dashes.len() % 2),
+            min_len: tolerance * MIN_DASH_TOLERANCES,
+            offset: 0.0,
+            dash: 0,
+            remaining: 0.0,
+            first: Point::zero(),
+            last: Point::zero(),
+            open: false,
+        };
+        let total: f32 = (0..dasher.len).map(|dash| dasher.dash_len(dash)).sum();
+        dasher.offset = self.dash_offset.rem_euclid(total);
+        for event in path.into_iter().flattened(tolerance) {
+            match event {
+                PathEvent::Begin { at } => dasher.begin(at),
+                PathEvent::Line { from, to } => dasher.line(from, to),
+                PathEvent::End { last, first, close } => {
+                    if close {
+                        dasher.line(last, first);
+                    }
+                    dasher.end();
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+pub fn stroke_style(width: f32) -> StrokeStyle {
+    StrokeStyle::new(width)
+}
+
+struct Dasher<'a> {
+    events: &'a mut Vec<PathEvent>,
+    pattern: &'a [f32],
+    len: usize,
+    min_len: f32,
+    offset: f32,
+    dash: usize,
+    remaining: f32,
+    first: Point,
+    last: Point,
+    open: bool,
+}
+
+impl Dasher<'_> {
+    fn begin(&mut self, at: Point) {
+        self.dash = 0;
+        self.remaining = self.dash_len(0);
+        let mut offset = self.offset;
+        while offset > self.remaining {
+            offset -= self.remaining;
+            self.next();
+        }
+        self.remaining -= offset;
+        if self.drawing() {
+            self.start(at);
+        }
+    }
+
+    fn line(&mut self, from: Point, to: Point) {
+        let len = (to - from).length();
+        let mut at = 0.0;
+        while len - at > self.remaining {
+            at += self.remaining;
+            let point = from.lerp(to, at / len);
+            if self.drawing() {
+                self.line_to(point);
+                self.stop();
+            }
+            self.next();
+            if self.drawing() {
+                self.start(point);
+            }
+        }
+        self.remaining -= len - at;
+        if self.drawing() {
+            self.line_to(to);
+        }
+    }
+
+    fn end(&mut self) {
+        self.stop();
+    }
+
+    fn drawing(&self) -> bool {
+        self.dash & 1 == 0
+    }
+
+    fn next(&mut self) {
+        self.dash = (self.dash + 1) % self.len;
+        self.remaining = self.dash_len(self.dash);
+    }
+
+    fn dash_len(&self, dash: usize) -> f32 {
+        self.pattern[dash % self.pattern.len()].max(self.min_len)
+    }
+
+    fn start(&mut self, at: Point) {
+        self.events.push(PathEvent::Begin { at });
+        self.first = at;
+        self.last = at;
+        self.open = true;
+    }
+
+    fn line_to(&mut self, to: Point) {
+        if to != self.last {
+            self.events.push(PathEvent::Line {
+                from: self.last,
+                to,
+            });
+            self.last = to;
+        }
+    }
+
+    fn stop(&mut self) {
+        if self.open {
+            self.events.push(PathEvent::End {
+                last: self.last,
+                first: self.first,
+                close: false,
+            });
+            self.open = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, lyon_tessellation::math::point};
+
+    fn dashes(style: &StrokeStyle, len: f32, tolerance: f32) -> Vec<(f32, f32)> {
+        let line = [
+            PathEvent::Begin {
+                at: point(0.0, 0.0),
+            },
+            PathEvent::Line {
+                from: point(0.0, 0.0),
+                to: point(len, 0.0),
+            },
+            PathEvent::End {
+                last: point(len, 0.0),
+                first: point(0.0, 0.0),
+                close: false,
+            },
+        ];
+        let mut events = Vec::new();
+        style.dash(line, tolerance, &mut events);
+        let round = |x: f32| (x * 1000.0).round() / 1000.0;
+        events
+            .iter()
+            .filter_map(|event| match event {
+                PathEvent::End { last, first, .. } => Some((round(first.x), round(last.x))),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn dash_pattern() {
+        let style = stroke_style(1.0).with_dashes([20.0, 10.0]);
+        assert_eq!(
+            dashes(&style, 100.0, 0.001),
+            [(0.0, 20.0), (30.0, 50.0), (60.0, 80.0), (90.0, 100.0)]
+        );
+    }
+
+    #[test]
+    fn dash_odd_pattern_repeats() {
+        let style = stroke_style(1.0).with_dashes([10.0, 5.0, 20.0]);
+        assert_eq!(
+            dashes(&style, 80.0, 0.001),
+            [(0.0, 10.0), (15.0, 35.0), (45.0, 50.0), (70.0, 80.0)]
+        );
+    }
+
+    #[test]
+    fn dash_offset() {
+        let style = stroke_style(1.0)
+            .with_dashes([20.0, 10.0])
+            .with_dash_offset(-5.0);
+        assert_eq!(dashes(&style, 40.0, 0.001), [(5.0, 25.0), (35.0, 40.0)]);
+    }
+
+    #[test]
+    fn dash_lengths_are_clamped_to_tolerance() {
+        let style = stroke_style(1.0).with_dashes([1e-6, 1e-6]);
+        assert_eq!(dashes(&style, 1.0, 0.0025), [(0.0, 0.25), (0.5, 0.75)]);
+    }
+}
@@ -0,0 +1,416 @@
+use {
+    crate::{
+        layers::{color, instance, Color, Instance},
+        tesselator::TessellationChain,
+    },
+    glam::{vec2, Affine2},
+    lyon_tessellation::{
+        math::{point, vector, Angle},
+        path::{builder::SvgPathBuilder, ArcFlags, Path},
+    },
+    roxmltree::{Document, Node},
+    std::{error::Error, fmt, fs, io, path::Path as FilePath, str::FromStr},
+    svgtypes::{Length, Paint, PathParser, PathSegment, PointsParser, Transform},
+};
+
+#[derive(Debug)]
+pub enum SvgError {
+    Io(io::Error),
+    Xml(roxmltree::Error),
+    Syntax(svgtypes::Error),
+}
+
+impl fmt::Display for SvgError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "failed to read svg: {}", error),
+            Self::Xml(error) => write!(f, "invalid svg document: {}", error),
+            Self::Syntax(error) => write!(f, "invalid svg attribute: {}", error),
+        }
+    }
+}
+
+impl Error for SvgError {}
+
+impl From<io::Error> for SvgError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<roxmltree::Error> for SvgError {
+    fn from(error: roxmltree::Error) -> Self {
+        Self::Xml(error)
+    }
+}
+
+impl From<svgtypes::Error> for SvgError {
+    fn from(error: svgtypes::Error) -> Self {
+        Self::Syntax(error)
+    }
+}
+
+pub struct SvgShape {
+    pub path: Path,
+    pub transform: Affine2,
+    pub fill: Option<Color>,
+    pub stroke: Option<Color>,
+    pub stroke_width: f32,
+}
+
+impl SvgShape {
+    pub fn fill_instance(&self) -> Option<Instance> {
+        self.fill.map(|fill| instance(self.transform, fill))
+    }
+
+    pub fn stroke_instance(&self) -> Option<Instance> {
+        self.stroke.map(|stroke| instance(self.transform, stroke))
+    }
+}
+
+pub fn parse_svg_path(d: &str) -> Result<Path, SvgError> {
+    let mut chain = TessellationChain::default();
+    append_path(&mut chain, d)?;
+    Ok(chain.finish())
+}
+
+pub fn parse_svg(text: &str) -> Result<Vec<SvgShape>, SvgError> {
+    let document = Document::parse(text)?;
+    let mut shapes = Vec::new();
+    visit(document.root_element(), Style::default(), &mut shapes)?;
+    Ok(shapes)
+}
+
+pub fn load_svg(path: impl AsRef<FilePath>) -> Result<Vec<SvgShape>, SvgError> {
+    parse_svg(&fs::read_to_string(path)?)
+}
+
+#[derive(Copy, Clone)]
+struct Style {
+    transform: Affine2,
+    fill: Option<Color>,
+    stroke: Option<Color>,
+    stroke_width: f32,
+    fill_opacity: f32,
+    stroke_opacity: f32,
+    opacity: f32,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self {
+            transform: Affine2::IDENTITY,
+            fill: Some(color(0, 0, 0, 255)),
+            stroke: None,
+            stroke_width: 1.0,
+            fill_opacity: 1.0,
+            stroke_opacity: 1.0,
+            opacity: 1.0,
+        }
+    }
+}
+
+fn visit(node: Node<'_, '_>, parent: Style, shapes: &mut Vec<SvgShape>) -> Result<(), SvgError> {
+    if !node.is_element() || attribute(node, "display") == Some("none") {
+        return Ok(());
+    }
+    let name = node.tag_name().name();
+    if matches!(
+        name,
+        "defs" | "clipPath" | "mask" | "symbol" | "pattern" | "linearGradient" | "radialGradient"
+    ) {
+        return Ok(());
+    }
+    let style = style(node, parent)?;
+    let mut chain = TessellationChain::default();
+    match name {
+        "path" => append_path(&mut chain, attribute(node, "d").unwrap_or_default())?,
+        "rect" => {
+            let (x, y) = (length(node, "x")?, length(node, "y")?);
+            let (width, height) = (length(node, "width")?, length(node, "height")?);
+            let radius = match (length(node, "rx")?, length(node, "ry")?) {
+                (rx, 0.0) => rx,
+                (0.0, ry) => ry,
+                (rx, ry) => rx.min(ry),
+            };
+            chain.rounded_rect(vec2(x, -y - height), vec2(x + width, -y), radius);
+        }
+        "circle" => {
+            let center = vec2(length(node, "cx")?, -length(node, "cy")?);
+            chain.circle(center, length(node, "r")?);
+        }
+        "ellipse" => {
+            let center = vec2(length(node, "cx")?, -length(node, "cy")?);
+            let radii = vec2(length(node, "rx")?, length(node, "ry")?);
+            chain.ellipse(center, radii, 0.0);
+        }
+        "line" => {
+            chain.chain([
+                vec2(length(node, "x1")?, -length(node, "y1")?),
+                vec2(length(node, "x2")?, -length(node, "y2")?),
+            ]);
+        }
+        "polyline" | "polygon" => {
+            let points = attribute(node, "points").unwrap_or_default();
+            chain.chain(PointsParser::from(points).map(|(x, y)| vec2(x as _, -y as f32)));
+            if name == "polygon" {
+                chain.close();
+            }
+        }
+        _ => {
+            for child in node.children() {
+                visit(child, style, shapes)?;
+            }
+            return Ok(());
+        }
+    }
+    shapes.push(SvgShape {
+        path: chain.finish(),
+        transform: style.transform,
+        fill: style
+            .fill
+            .map(|fill| fade(fill, style.fill_opacity * style.opacity)),
+        stroke: style
+            .stroke
+            .map(|stroke| fade(stroke, style.stroke_opacity * style.opacity)),
+        stroke_width: style.stroke_width,
+    });
+    Ok(())
+}
+
+fn style(node: Node<'_, '_>, parent: Style) -> Result<Style, SvgError> {
+    let mut style = parent;
+    if let Some(transform) = node.attribute("transform") {
+        let Transform { a, b, c, d, e, f } = Transform::from_str(transform)?;
+        let flipped = [a, -b, -c, d, e, -f].map(|value| value as f32);
+        style.transform = parent.transform * Affine2::from_cols_array(&flipped);
+    }
+    if let Some(fill) = attribute(node, "fill") {
+        style.fill = paint(fill, parent.fill)?;
+    }
+    if let Some(stroke) = attribute(node, "stroke") {
+        style.stroke = paint(stroke, parent.stroke)?;
+    }
+    if let Some(width) = attribute(node, "stroke-width") {
+        style.stroke_width = Length::from_str(width)?.number as _;
+    }
+    if let Some(opacity) = attribute(node, "fill-opacity") {
+        style.fill_opacity = number(opacity)?;
+    }
+    if let Some(opacity) = attribute(node, "stroke-opacity") {
+        style.stroke_opacity = number(opacity)?;
+    }
+    if let Some(opacity) = attribute(node, "opacity") {
+        style.opacity *= number(opacity)?;
+    }
+    Ok(style)
+}
+
+fn attribute<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    node.attribute("style")
+        .and_then(|style| {
+            style.split(';').find_map(|declaration| {
+                let (key, value) = declaration.split_once(':')?;
+                (key.trim() == name).then(|| value.trim())
+            })
+        })
+        .or_else(|| node.attribute(name))
+}
+
+fn length(node: Node<'_, '_>, name: &str) -> Result<f32, SvgError> {
+    Ok(match attribute(node, name) {
+        Some(value) => Length::from_str(value)?.number as _,
+        None => 0.0,
+    })
+}
+
+fn number(value: &str) -> Result<f32, SvgError> {
+    Ok(svgtypes::Number::from_str(value)?.0.clamp(0.0, 1.0) as _)
+}
+
+fn paint(value: &str, inherited: Option<Color>) -> Result<Option<Color>, SvgError> {
+    Ok(match Paint::from_str(value)? {
+        Paint::None => None,
+        Paint::Color(c) => Some(color(c.red, c.green, c.blue, c.alpha)),
+        Paint::FuncIRI(_, Some(svgtypes::PaintFallback::Color(c))) => {
+            Some(color(c.red, c.green, c.blue, c.alpha))
+        }
+        _ => inherited,
+    })
+}
+
+fn fade(color: Color, opacity: f32) -> Color {
+    color.with_a((color.a as f32 * opacity).round() as u8)
+}
+
+fn append_path(chain: &mut TessellationChain, d: &str) -> Result<(), SvgError> {
+    let builder = &mut chain.0;
+    let to = |x: f64, y: f64| point(x as _, -y as f32);
+    let by = |x: f64, y: f64| vector(x as _, -y as f32);
+    for segment in PathParser::from(d) {
+        match segment? {
+            PathSegment::MoveTo { abs: true, x, y } => SvgPathBuilder::move_to(builder, to(x, y)),
+            PathSegment::MoveTo { abs: false, x, y } => builder.relative_move_to(by(x, y)),
+            PathSegment::LineTo { abs: true, x, y } => SvgPathBuilder::line_to(builder, to(x, y)),
+            PathSegment::LineTo { abs: false, x, y } => builder.relative_line_to(by(x, y)),
+            PathSegment::HorizontalLineTo { abs: true, x } => builder.horizontal_line_to(x as _),
+            PathSegment::HorizontalLineTo { abs: false, x } => {
+                builder.relative_horizontal_line_to(x as _)
+            }
+            PathSegment::VerticalLineTo { abs: true, y } => builder.vertical_line_to(-y as _),
+            PathSegment::VerticalLineTo { abs: false, y } => {
+                builder.relative_vertical_line_to(-y as _)
+            }
+            PathSegment::CurveTo {
+                abs: true,
+                x1,
+                y1,
+                x2,
+                y2,
+                x,
+                y,
+            } => SvgPathBuilder::cubic_bezier_to(builder, to(x1, y1), to(x2, y2), to(x, y)),
+            PathSegment::CurveTo {
+                abs: false,
+                x1,
+                y1,
+                x2,
+                y2,
+                x,
+                y,
+            } => builder.relative_cubic_bezier_to(by(x1, y1), by(x2, y2), by(x, y)),
+            PathSegment::SmoothCurveTo {
+                abs: true,
+                x2,
+                y2,
+                x,
+                y,
+            } => builder.smooth_cubic_bezier_to(to(x2, y2), to(x, y)),
+            PathSegment::SmoothCurveTo {
+                abs: false,
+                x2,
+                y2,
+                x,
+                y,
+            } => builder.smooth_relative_cubic_bezier_to(by(x2, y2), by(x, y)),
+            PathSegment::Quadratic {
+                abs: true,
+                x1,
+                y1,
+                x,
+                y,
+            } => SvgPathBuilder::quadratic_bezier_to(builder, to(x1, y1), to(x, y)),
+            PathSegment::Quadratic {
+                abs: false,
+                x1,
+                y1,
+                x,
+                y,
+            } => builder.relative_quadratic_bezier_to(by(x1, y1), by(x, y)),
+            PathSegment::SmoothQuadratic { abs: true, x, y } => {
+                builder.smooth_quadratic_bezier_to(to(x, y))
+            }
+            PathSegment::SmoothQuadratic { abs: false, x, y } => {
+                builder.smooth_relative_quadratic_bezier_to(by(x, y))
+            }
+            PathSegment::EllipticalArc {
+                abs,
+                rx,
+                ry,
+                x_axis_rotation,
+                large_arc,
+                sweep,
+                x,
+                y,
+            } => {
+                let radii = vector(rx as _, ry as _);
+                let x_rotation = Angle::degrees(-x_axis_rotation as f32);
+                let flags = ArcFlags {
+                    large_arc,
+                    sweep: !sweep,
+                };
+                if abs {
+                    builder.arc_to(radii, x_rotation, flags, to(x, y))
+                } else {
+                    builder.relative_arc_to(radii, x_rotation, flags, by(x, y))
+                }
+            }
+            PathSegment::ClosePath { .. } => SvgPathBuilder::close(builder),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        glam::Vec2,
+        lyon_tessellation::path::{iterator::PathIterator, PathEvent},
+    };
+
+    fn points(path: &Path) -> Vec<Vec2> {
+        path.iter()
+            .flattened(0.01)
+            .filter_map(|event| match event {
+                PathEvent::Begin { at } => Some(vec2(at.x, at.y)),
+                PathEvent::Line { to, .. } => Some(vec2(to.x, to.y)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parse_lines_and_flip_y() {
+        let path = parse_svg_path("M 10 20 L 30 20 l 0 10 H 10 Z").unwrap();
+        assert_eq!(
+            points(&path),
+            [
+                vec2(10.0, -20.0),
+                vec2(30.0, -20.0),
+                vec2(30.0, -30.0),
+                vec2(10.0, -30.0),
+            ]
+        );
+        assert!(matches!(
+            path.iter().last(),
+            Some(PathEvent::End { close: true, .. })
+        ));
+    }
+
+    #[test]
+    fn parse_arcs_with_svg_sweep() {
+        let points = points(&parse_svg_path("M 0 0 A 10 10 0 0 1 20 0").unwrap());
+        assert!(points.last().unwrap().abs_diff_eq(vec2(20.0, 0.0), 1e-3));
+        assert!(points
+            .iter()
+            .all(|point| (point.distance(vec2(10.0, 0.0)) - 10.0).abs() < 0.1));
+        assert!(points.iter().all(|point| point.y > -1e-3));
+        assert!(points.iter().any(|point| point.y > 9.9));
+    }
+
+    #[test]
+    fn reject_invalid_paths() {
+        assert!(matches!(parse_svg_path("M 10 x"), Err(SvgError::Syntax(_))));
+    }
+
+    #[test]
+    fn parse_nested_transforms_and_styles() {
+        let shapes = parse_svg(
+            r#"<svg xmlns="http://www.w3.org/2000/svg">
+                <defs><rect width="1" height="1"/></defs>
+                <g transform="translate(10 20)" style="fill: red; opacity: 0.5">
+                    <rect width="4" height="2" transform="scale(2)" stroke="blue"/>
+                    <circle r="1" display="none"/>
+                </g>
+            </svg>"#,
+        )
+        .unwrap();
+        assert_eq!(shapes.len(), 1);
+        let shape = &shapes[0];
+        let corner = shape.transform.transform_point2(vec2(1.0, -1.0));
+        assert!(corner.abs_diff_eq(vec2(12.0, -22.0), 1e-4), "{}", corner);
+        assert_eq!(shape.fill, Some(color(255, 0, 0, 128)));
+        assert_eq!(shape.stroke, Some(color(0, 0, 255, 128)));
+    }
+}
@@ -1,19 +1,38 @@
 use {
-    crate::layers::Layers,
-    glam::Vec2,
+    crate::{
+        gradient::Gradient,
+        layers::{Attributes, CapacityError, Color, Index, LayerBuffer, LayerMut, Layers},
+        stroke::StrokeStyle,
+        text::{Align, Font},
+    },
+    glam::{vec2, Mat2, Vec2},
     lyon_tessellation::{
+        math::{vector, Angle},
         path::{
-            builder::WithSvg,
+            builder::{SvgPathBuilder, WithSvg},
             path::{Builder as PathBuilder, Path},
-            PathEvent,
+            ArcFlags, PathEvent,
         },
-        BuffersBuilder, FillOptions, FillTessellator, FillVertex, LineCap, LineJoin, StrokeOptions,
-        StrokeTessellator, StrokeVertex, VertexBuffers,
+        Count, FillGeometryBuilder, FillOptions, FillTessellator, FillVertex, GeometryBuilder,
+        GeometryBuilderError, StrokeGeometryBuilder, StrokeOptions, StrokeTessellator,
+        StrokeVertex, TessellationResult, VertexBuffers, VertexId,
+    },
+    std::{
+        collections::HashMap,
+        error::Error,
+        f32::consts::TAU,
+        fmt,
+        iter::once,
+        mem::{replace, take},
     },
-    std::mem::replace,
 };
 
-pub struct TessellationChain(WithSvg<PathBuilder>);
+const KAPPA: f32 = 0.552_284_8;
+const MAX_SUBDIVISIONS: usize = 16;
+const MAX_SPLITS: usize = 4096;
+const TOLERANCE: f32 = 0.0001;
+
+pub struct TessellationChain(pub(crate) WithSvg<PathBuilder>);
 
 impl Default for TessellationChain {
     fn default() -> Self {
@@ -41,11 +60,119 @@ impl TessellationChain {
         self
     }
 
+    pub fn move_to(&mut self, to: Vec2) -> &mut Self {
+        self.0.move_to(mint_convert(to));
+        self
+    }
+
+    pub fn line_to(&mut self, to: Vec2) -> &mut Self {
+        self.0.line_to(mint_convert(to));
+        self
+    }
+
+    pub fn quadratic_to(&mut self, ctrl: Vec2, to: Vec2) -> &mut Self {
+        self.0
+            .quadratic_bezier_to(mint_convert(ctrl), mint_convert(to));
+        self
+    }
+
+    pub fn cubic_to(&mut self, ctrl1: Vec2, ctrl2: Vec2, to: Vec2) -> &mut Self {
+        self.0
+            .cubic_bezier_to(mint_convert(ctrl1), mint_convert(ctrl2), mint_convert(to));
+        self
+    }
+
+    pub fn arc_to(
+        &mut self,
+        radii: Vec2,
+        x_rotation: f32,
+        large_arc: bool,
+        sweep: bool,
+        to: Vec2,
+    ) -> &mut Self {
+        SvgPathBuilder::arc_to(
+            &mut self.0,
+            vector(radii.x, radii.y),
+            Angle::radians(x_rotation),
+            ArcFlags { large_arc, sweep },
+            mint_convert(to),
+        );
+        self
+    }
+
     pub fn close(&mut self) -> &mut Self {
         self.0.close();
         self
     }
 
+    pub fn circle(&mut self, center: Vec2, radius: f32) -> &mut Self {
+        self.ellipse(center, Vec2::splat(radius), 0.0)
+    }
+
+    pub fn ellipse(&mut self, center: Vec2, radii: Vec2, rotation: f32) -> &mut Self {
+        let rotation = Mat2::from_angle(rotation);
+        let at = |angle: f32| center + rotation * (radii * Vec2::new(angle.cos(), angle.sin()));
+        let tangent = |angle: f32| rotation * (radii * Vec2::new(-angle.sin(), angle.cos()));
+        self.move_to(at(0.0));
+        for quarter in 0..4 {
+            let from = quarter as f32 * TAU / 4.0;
+            let to = from + TAU / 4.0;
+            self.cubic_to(
+                at(from) + tangent(from) * KAPPA,
+                at(to) - tangent(to) * KAPPA,
+                at(to),
+            );
+        }
+        self.close()
+    }
+
+    pub fn rounded_rect(&mut self, min: Vec2, max: Vec2, radius: f32) -> &mut Self {
+        let radius = radius.clamp(0.0, ((max - min) * 0.5).abs().min_element());
+        let corners = [
+            (vec2(max.x, min.y), vec2(-1.0, 0.0), vec2(0.0, 1.0)),
+            (max, vec2(0.0, -1.0), vec2(-1.0, 0.0)),
+            (vec2(min.x, max.y), vec2(1.0, 0.0), vec2(0.0, -1.0)),
+            (min, vec2(0.0, 1.0), vec2(1.0, 0.0)),
+        ];
+        self.move_to(min + vec2(radius, 0.0));
+        for (corner, into, out_of) in corners {
+            let from = corner + into * radius;
+            let to = corner + out_of * radius;
+            self.line_to(from);
+            if radius > 0.0 {
+                self.cubic_to(
+                    from + (corner - from) * KAPPA,
+                    to + (corner - to) * KAPPA,
+                    to,
+                );
+            }
+        }
+        self.close()
+    }
+
+    pub fn polygon(&mut self, center: Vec2, radius: f32, sides: usize) -> &mut Self {
+        self.star_points(center, [radius].repeat(sides.max(3)))
+    }
+
+    pub fn star(
+        &mut self,
+        center: Vec2,
+        outer_radius: f32,
+        inner_radius: f32,
+        points: usize,
+    ) -> &mut Self {
+        self.star_points(center, [outer_radius, inner_radius].repeat(points.max(2)))
+    }
+
+    fn star_points(&mut self, center: Vec2, radii: Vec<f32>) -> &mut Self {
+        let step = TAU / radii.len() as f32;
+        self.chain(radii.iter().enumerate().map(|(i, radius)| {
+            let angle = TAU / 4.0 + i as f32 * step;
+            center + Vec2::new(angle.cos(), angle.sin()) * *radius
+        }))
+        .close()
+    }
+
     pub fn finish(&mut self) -> Path {
         replace(&mut self.0, PathBuilder::default().with_svg()).build()
     }
@@ -55,67 +182,507 @@ pub fn tessellation_chain(capacity: usize) -> TessellationChain {
     TessellationChain::with_capacity(capacity)
 }
 
-macro_rules! tessellate {
-    ($tessellator:ident<$vertex:ident<$($l:lifetime),+>>,$self:ident,$path:expr,$options:expr) => {{
-        $self.0.vertices.clear();
-        $self.0.indices.clear();
-        let mut buffers_builder = BuffersBuilder::new(&mut $self.0,
-            |p: $vertex<$($l),+>| mint_convert(p.position())
-        );
-        let mut t = $tessellator::new();
-        let _ = t.tessellate(
-            $path,
-            &$options,
-            &mut buffers_builder
-        );
-    }};
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum TessellationError {
+    UnsupportedParameter,
+    InvalidVertex,
+    TooManyVertices,
+    Internal,
+    Capacity(CapacityError),
+}
+
+impl fmt::Display for TessellationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedParameter => f.write_str("unsupported tessellation parameter"),
+            Self::InvalidVertex => f.write_str("path contains an invalid vertex"),
+            Self::TooManyVertices => f.write_str("tessellation produced too many vertices"),
+            Self::Internal => f.write_str("internal tessellation error"),
+            Self::Capacity(error) => error.fmt(f),
+        }
+    }
+}
+
+impl Error for TessellationError {}
+
+impl From<lyon_tessellation::TessellationError> for TessellationError {
+    fn from(error: lyon_tessellation::TessellationError) -> Self {
+        match error {
+            lyon_tessellation::TessellationError::UnsupportedParamater => {
+                Self::UnsupportedParameter
+            }
+            lyon_tessellation::TessellationError::InvalidVertex => Self::InvalidVertex,
+            lyon_tessellation::TessellationError::TooManyVertices => Self::TooManyVertices,
+            lyon_tessellation::TessellationError::Internal(_) => Self::Internal,
+        }
+    }
+}
+
+impl From<CapacityError> for TessellationError {
+    fn from(error: CapacityError) -> Self {
+        Self::Capacity(error)
+    }
 }
 
-pub struct Tessellator(VertexBuffers<Vec2, u16>);
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct Shape {
+    pub vertices: std::ops::Range<usize>,
+    pub indices: std::ops::Range<usize>,
+    pub path_id: u32,
+}
+
+pub struct Tessellator {
+    buffers: VertexBuffers<Vec2, Index>,
+    attributes: Vec<Attributes>,
+    with_attributes: bool,
+    colors: Vec<Color>,
+    gradient: Option<Gradient>,
+    path_id: u32,
+    fill: FillTessellator,
+    stroke: StrokeTessellator,
+    events: Vec<PathEvent>,
+}
+
+impl Default for Tessellator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl Tessellator {
+    pub fn new() -> Self {
+        Self::with_capacity(0, 0)
+    }
+
+    pub fn with_capacity(vertices: usize, indices: usize) -> Self {
+        Self {
+            buffers: VertexBuffers::with_capacity(vertices, indices),
+            attributes: Vec::new(),
+            with_attributes: false,
+            colors: Vec::with_capacity(vertices),
+            gradient: None,
+            path_id: 0,
+            fill: FillTessellator::new(),
+            stroke: StrokeTessellator::new(),
+            events: Vec::new(),
+        }
+    }
+
     pub fn vertices(&self) -> &[Vec2] {
-        &self.0.vertices
+        &self.buffers.vertices
     }
 
-    pub fn indices(&self) -> &[u16] {
-        &self.0.indices
+    pub fn indices(&self) -> &[Index] {
+        &self.buffers.indices
     }
 
-    pub fn fill_clear(&mut self, path: impl IntoIterator<Item = PathEvent>) {
-        self.fill_clear_with(path, FillOptions::default().with_tolerance(0.0001))
+    pub fn attributes(&self) -> &[Attributes] {
+        &self.attributes
     }
 
-    pub fn stroke_clear(&mut self, path: impl IntoIterator<Item = PathEvent>, width: f32) {
-        self.stroke_clear_with(
-            path,
-            StrokeOptions::default()
-                .with_tolerance(0.0001)
-                .with_line_join(LineJoin::Bevel)
-                .with_line_cap(LineCap::Butt)
-                .with_line_width(width),
-        )
+    pub fn set_attributes(&mut self, enabled: bool) {
+        self.with_attributes = enabled;
+        self.attributes.clear();
+        if enabled {
+            self.attributes
+                .resize(self.buffers.vertices.len(), Attributes::default());
+        }
+    }
+
+    pub fn colors(&self) -> &[Color] {
+        &self.colors
+    }
+
+    pub fn gradient(&self) -> Option<&Gradient> {
+        self.gradient.as_ref()
+    }
+
+    pub fn set_gradient(&mut self, gradient: Option<Gradient>) {
+        self.gradient = gradient;
+    }
+
+    pub fn path_id(&self) -> u32 {
+        self.path_id
+    }
+
+    pub fn set_path_id(&mut self, path_id: u32) {
+        self.path_id = path_id;
+    }
+
+    pub fn clear(&mut self) {
+        self.buffers.vertices.clear();
+        self.buffers.indices.clear();
+        self.attributes.clear();
+        self.colors.clear();
+        self.path_id = 0;
+    }
+
+    pub fn fill_clear(
+        &mut self,
+        path: impl IntoIterator<Item = PathEvent>,
+    ) -> Result<(), TessellationError> {
+        self.clear();
+        self.fill(path)?;
+        Ok(())
+    }
+
+    pub fn stroke_clear(
+        &mut self,
+        path: impl IntoIterator<Item = PathEvent>,
+        width: f32,
+    ) -> Result<(), TessellationError> {
+        self.clear();
+        self.stroke(path, width)?;
+        Ok(())
+    }
+
+    pub fn stroke_styled_clear(
+        &mut self,
+        path: impl IntoIterator<Item = PathEvent>,
+        style: &StrokeStyle,
+    ) -> Result<(), TessellationError> {
+        self.clear();
+        self.stroke_styled(path, style)?;
+        Ok(())
+    }
+
+    pub fn text_clear(
+        &mut self,
+        font: &Font,
+        text: &str,
+        size: f32,
+        align: Align,
+    ) -> Result<(), TessellationError> {
+        self.clear();
+        self.text(font, text, size, align)?;
+        Ok(())
     }
 
     pub fn fill_clear_with(
         &mut self,
         path: impl IntoIterator<Item = PathEvent>,
         options: FillOptions,
-    ) {
-        tessellate!(FillTessellator<FillVertex<'_>>, self, path, options);
+    ) -> Result<(), TessellationError> {
+        self.clear();
+        self.fill_with(path, options)?;
+        Ok(())
     }
 
     pub fn stroke_clear_with(
         &mut self,
         path: impl IntoIterator<Item = PathEvent>,
         options: StrokeOptions,
-    ) {
-        tessellate!(StrokeTessellator<StrokeVertex<'_, '_>>, self, path, options);
+    ) -> Result<(), TessellationError> {
+        self.clear();
+        self.stroke_with(path, options)?;
+        Ok(())
+    }
+
+    pub fn fits_in(&self, layer: &LayerMut<'_>) -> Result<(), CapacityError> {
+        let checks = [
+            (
+                LayerBuffer::Vertices,
+                self.buffers.vertices.len(),
+                layer.vertices_room(),
+            ),
+            (
+                LayerBuffer::Indices,
+                self.buffers.indices.len(),
+                layer.indices_room(),
+            ),
+        ];
+        for (buffer, len, max) in checks {
+            if len > max {
+                return Err(CapacityError {
+                    layer: layer.index(),
+                    buffer,
+                    written: 0,
+                    dropped: Some(len),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    pub fn fill(
+        &mut self,
+        path: impl IntoIterator<Item = PathEvent>,
+    ) -> Result<Shape, TessellationError> {
+        self.fill_with(path, fill_options())
+    }
+
+    pub fn stroke(
+        &mut self,
+        path: impl IntoIterator<Item = PathEvent>,
+        width: f32,
+    ) -> Result<Shape, TessellationError> {
+        self.stroke_with(path, stroke_options(width))
+    }
+
+    pub fn stroke_styled(
+        &mut self,
+        path: impl IntoIterator<Item = PathEvent>,
+        style: &StrokeStyle,
+    ) -> Result<Shape, TessellationError> {
+        let options = style.options(TOLERANCE);
+        if !style.is_dashed() {
+            return self.stroke_with(path, options);
+        }
+        let mut events = take(&mut self.events);
+        events.clear();
+        style.dash(path, TOLERANCE, &mut events);
+        let shape = self.stroke_with(events.iter().copied(), options);
+        self.events = events;
+        shape
+    }
+
+    pub fn text(
+        &mut self,
+        font: &Font,
+        text: &str,
+        size: f32,
+        align: Align,
+    ) -> Result<Shape, TessellationError> {
+        let mut events = take(&mut self.events);
+        events.clear();
+        font.events(text, size, align, &mut events);
+        let shape = self.fill_with(events.iter().copied(), text_options(size));
+        self.events = events;
+        shape
+    }
+
+    pub fn fill_with(
+        &mut self,
+        path: impl IntoIterator<Item = PathEvent>,
+        options: FillOptions,
+    ) -> Result<Shape, TessellationError> {
+        let mut builder = BufferBuilder::new(
+            &mut self.buffers,
+            self.with_attributes.then_some(&mut self.attributes),
+            &mut self.colors,
+            self.gradient.as_ref(),
+            self.path_id,
+        );
+        let result = self.fill.tessellate(path, &options, &mut builder);
+        let shape = builder.finish(result)?;
+        let shape = self.subdivide(shape)?;
+        self.path_id += 1;
+        Ok(shape)
+    }
+
+    pub fn stroke_with(
+        &mut self,
+        path: impl IntoIterator<Item = PathEvent>,
+        options: StrokeOptions,
+    ) -> Result<Shape, TessellationError> {
+        let mut builder = BufferBuilder::new(
+            &mut self.buffers,
+            self.with_attributes.then_some(&mut self.attributes),
+            &mut self.colors,
+            self.gradient.as_ref(),
+            self.path_id,
+        );
+        let result = self.stroke.tessellate(path, &options, &mut builder);
+        let shape = builder.finish(result)?;
+        let shape = self.subdivide(shape)?;
+        self.path_id += 1;
+        Ok(shape)
+    }
+
+    pub fn fill_into(
+        &mut self,
+        layer: &mut LayerMut<'_>,
+        path: impl IntoIterator<Item = PathEvent>,
+    ) -> Result<Shape, TessellationError> {
+        self.fill_into_with(layer, path, fill_options())
+    }
+
+    pub fn stroke_into(
+        &mut self,
+        layer: &mut LayerMut<'_>,
+        path: impl IntoIterator<Item = PathEvent>,
+        width: f32,
+    ) -> Result<Shape, TessellationError> {
+        self.stroke_into_with(layer, path, stroke_options(width))
+    }
+
+    pub fn stroke_styled_into(
+        &mut self,
+        layer: &mut LayerMut<'_>,
+        path: impl IntoIterator<Item = PathEvent>,
+        style: &StrokeStyle,
+    ) -> Result<Shape, TessellationError> {
+        let options = style.options(TOLERANCE);
+        if !style.is_dashed() {
+            return self.stroke_into_with(layer, path, options);
+        }
+        let mut events = take(&mut self.events);
+        events.clear();
+        style.dash(path, TOLERANCE, &mut events);
+        let shape = self.stroke_into_with(layer, events.iter().copied(), options);
+        self.events = events;
+        shape
+    }
+
+    pub fn text_into(
+        &mut self,
+        layer: &mut LayerMut<'_>,
+        font: &Font,
+        text: &str,
+        size: f32,
+        align: Align,
+    ) -> Result<Shape, TessellationError> {
+        let mut events = take(&mut self.events);
+        events.clear();
+        font.events(text, size, align, &mut events);
+        let shape = self.fill_into_with(layer, events.iter().copied(), text_options(size));
+        self.events = events;
+        shape
+    }
+
+    pub fn fill_into_with(
+        &mut self,
+        layer: &mut LayerMut<'_>,
+        path: impl IntoIterator<Item = PathEvent>,
+        options: FillOptions,
+    ) -> Result<Shape, TessellationError> {
+        let mut builder = LayerBuilder::new(layer, self.gradient.as_ref(), self.path_id);
+        let result = self.fill.tessellate(path, &options, &mut builder);
+        let shape = builder.finish(result)?;
+        let shape = self.subdivide_into(layer, shape)?;
+        self.path_id += 1;
+        Ok(shape)
+    }
+
+    pub fn stroke_into_with(
+        &mut self,
+        layer: &mut LayerMut<'_>,
+        path: impl IntoIterator<Item = PathEvent>,
+        options: StrokeOptions,
+    ) -> Result<Shape, TessellationError> {
+        let mut builder = LayerBuilder::new(layer, self.gradient.as_ref(), self.path_id);
+        let result = self.stroke.tessellate(path, &options, &mut builder);
+        let shape = builder.finish(result)?;
+        let shape = self.subdivide_into(layer, shape)?;
+        self.path_id += 1;
+        Ok(shape)
+    }
+
+    fn subdivide(&mut self, shape: Shape) -> Result<Shape, TessellationError> {
+        let gradient = match &self.gradient {
+            Some(gradient) => gradient,
+            None => return Ok(shape),
+        };
+        let base = shape.vertices.start;
+        let mut positions = self.buffers.vertices[shape.vertices.clone()].to_vec();
+        let mut triangles = self.buffers.indices[shape.indices.clone()]
+            .chunks_exact(3)
+            .map(|t| [t[0], t[1], t[2]].map(|i| (i as usize - base) as u32))
+            .collect();
+        let splits = subdivide(&mut positions, &mut triangles, gradient);
+        if splits.is_empty() {
+            return Ok(shape);
+        }
+        if base + positions.len() > Index::MAX as usize + 1 {
+            self.buffers.vertices.truncate(base);
+            self.buffers.indices.truncate(shape.indices.start);
+            self.colors.truncate(base);
+            self.attributes.truncate(base.min(self.attributes.len()));
+            return Err(TessellationError::TooManyVertices);
+        }
+        let first = positions.len() - splits.len();
+        for (&position, [a, b]) in positions[first..].iter().zip(splits) {
+            self.buffers.vertices.push(position);
+            self.colors.push(gradient.color_at(position));
+            if self.with_attributes {
+                let (a, b) = (self.attributes[base + a], self.attributes[base + b]);
+                self.attributes.push(midpoint(a, b));
+            }
+        }
+        self.buffers.indices.truncate(shape.indices.start);
+        self.buffers.indices.extend(
+            triangles
+                .iter()
+                .flatten()
+                .map(|&i| (base + i as usize) as Index),
+        );
+        Ok(Shape {
+            vertices: base..self.buffers.vertices.len(),
+            indices: shape.indices.start..self.buffers.indices.len(),
+            path_id: shape.path_id,
+        })
+    }
+
+    fn subdivide_into(
+        &mut self,
+        layer: &mut LayerMut<'_>,
+        shape: Shape,
+    ) -> Result<Shape, TessellationError> {
+        let gradient = match &self.gradient {
+            Some(gradient) if layer.has_colors() => gradient,
+            _ => return Ok(shape),
+        };
+        let base = shape.vertices.start;
+        let mut positions = layer.vertices()[shape.vertices.clone()].to_vec();
+        let mut triangles = shape
+            .indices
+            .clone()
+            .step_by(3)
+            .map(|at| {
+                [at, at + 1, at + 2].map(|at| (layer.get_index(at).unwrap() as usize - base) as u32)
+            })
+            .collect();
+        let splits = subdivide(&mut positions, &mut triangles, gradient);
+        if splits.is_empty() {
+            return Ok(shape);
+        }
+        let first = positions.len() - splits.len();
+        let attributes = layer.attributes();
+        let mut data: Vec<(Vec2, Vec2, Attributes, Color)> = Vec::with_capacity(splits.len());
+        for (&position, [a, b]) in positions[first..].iter().zip(splits) {
+            let parent = |i: usize| {
+                if i < first {
+                    attributes[base + i]
+                } else {
+                    data[i - first].2
+                }
+            };
+            let attributes = if attributes.is_empty() {
+                Attributes::default()
+            } else {
+                midpoint(parent(a), parent(b))
+            };
+            data.push((
+                position,
+                Vec2::ZERO,
+                attributes,
+                gradient.color_at(position),
+            ));
+        }
+        let written = layer.extend_vertex_data(data).and_then(|_| {
+            layer.truncate_indices(shape.indices.start);
+            layer.extend_indices(
+                triangles
+                    .iter()
+                    .flatten()
+                    .map(|&i| (base + i as usize) as Index),
+            )
+        });
+        if let Err(error) = written {
+            layer.truncate_vertices(base);
+            layer.truncate_indices(shape.indices.start);
+            return Err(error.into());
+        }
+        Ok(Shape {
+            vertices: base..layer.vertices_len(),
+            indices: shape.indices.start..layer.indices_len(),
+            path_id: shape.path_id,
+        })
     }
 
     pub(crate) fn with_capacity_to_fit(chunk: &Layers) -> Self {
         if chunk.ranges.is_empty() {
-            return Self(VertexBuffers::new());
+            return Self::new();
         }
         let mut vertex_capacity = 0;
         let mut index_capacity = 0;
@@ -128,15 +695,442 @@ impl Tessellator {
                 prev = next;
             }
         }
-        vertex_capacity = vertex_capacity.max(chunk.vertices.len() as u16 - prev.vertex_start);
+        vertex_capacity = vertex_capacity.max(chunk.vertices.len() as Index - prev.vertex_start);
         index_capacity = index_capacity.max(chunk.indices.len() as u32 - prev.index_start);
-        Self(VertexBuffers::with_capacity(
-            vertex_capacity as _,
-            index_capacity as _,
-        ))
+        Self::with_capacity(vertex_capacity as _, index_capacity as _)
+    }
+}
+
+fn fill_options() -> FillOptions {
+    FillOptions::default().with_tolerance(TOLERANCE)
+}
+
+fn stroke_options(width: f32) -> StrokeOptions {
+    StrokeStyle::new(width).options(TOLERANCE)
+}
+
+fn text_options(size: f32) -> FillOptions {
+    FillOptions::non_zero().with_tolerance(size * 0.001)
+}
+
+struct BufferBuilder<'l> {
+    buffers: &'l mut VertexBuffers<Vec2, Index>,
+    attributes: Option<&'l mut Vec<Attributes>>,
+    colors: &'l mut Vec<Color>,
+    gradient: Option<&'l Gradient>,
+    path_id: u32,
+    vertex_start: usize,
+    index_start: usize,
+}
+
+impl<'l> BufferBuilder<'l> {
+    fn new(
+        buffers: &'l mut VertexBuffers<Vec2, Index>,
+        attributes: Option<&'l mut Vec<Attributes>>,
+        colors: &'l mut Vec<Color>,
+        gradient: Option<&'l Gradient>,
+        path_id: u32,
+    ) -> Self {
+        Self {
+            vertex_start: buffers.vertices.len(),
+            index_start: buffers.indices.len(),
+            buffers,
+            attributes,
+            colors,
+            gradient,
+            path_id,
+        }
+    }
+
+    fn add_vertex(
+        &mut self,
+        position: Vec2,
+        normal: Vec2,
+        advancement: f32,
+    ) -> Result<VertexId, GeometryBuilderError> {
+        let id = self.buffers.vertices.len();
+        if id > Index::MAX as usize {
+            return Err(GeometryBuilderError::TooManyVertices);
+        }
+        self.buffers.vertices.push(position);
+        self.colors.push(paint(self.gradient, position));
+        if let Some(attributes) = &mut self.attributes {
+            attributes.push(Attributes::new(normal, advancement, self.path_id));
+        }
+        Ok(VertexId(id as _))
+    }
+
+    fn truncate(&mut self) {
+        self.buffers.vertices.truncate(self.vertex_start);
+        self.buffers.indices.truncate(self.index_start);
+        self.colors.truncate(self.vertex_start);
+        if let Some(attributes) = &mut self.attributes {
+            attributes.truncate(self.vertex_start);
+        }
+    }
+
+    fn finish(mut self, result: TessellationResult) -> Result<Shape, TessellationError> {
+        match result {
+            Ok(_) => Ok(Shape {
+                vertices: self.vertex_start..self.buffers.vertices.len(),
+                indices: self.index_start..self.buffers.indices.len(),
+                path_id: self.path_id,
+            }),
+            Err(error) => {
+                self.truncate();
+                Err(error.into())
+            }
+        }
+    }
+}
+
+impl GeometryBuilder for BufferBuilder<'_> {
+    fn begin_geometry(&mut self) {}
+
+    fn end_geometry(&mut self) -> Count {
+        Count {
+            vertices: (self.buffers.vertices.len() - self.vertex_start) as _,
+            indices: (self.buffers.indices.len() - self.index_start) as _,
+        }
+    }
+
+    fn add_triangle(&mut self, a: VertexId, b: VertexId, c: VertexId) {
+        self.buffers
+            .indices
+            .extend([a, b, c].map(|id| id.0 as Index));
+    }
+
+    fn abort_geometry(&mut self) {
+        self.truncate();
+    }
+}
+
+impl FillGeometryBuilder for BufferBuilder<'_> {
+    fn add_fill_vertex(&mut self, vertex: FillVertex) -> Result<VertexId, GeometryBuilderError> {
+        self.add_vertex(mint_convert(vertex.position()), Vec2::ZERO, 0.0)
+    }
+}
+
+impl StrokeGeometryBuilder for BufferBuilder<'_> {
+    fn add_stroke_vertex(
+        &mut self,
+        vertex: StrokeVertex,
+    ) -> Result<VertexId, GeometryBuilderError> {
+        let normal = vertex.normal();
+        self.add_vertex(
+            mint_convert(vertex.position()),
+            vec2(normal.x, normal.y),
+            vertex.advancement(),
+        )
+    }
+}
+
+struct LayerBuilder<'l, 'a> {
+    layer: &'l mut LayerMut<'a>,
+    gradient: Option<&'l Gradient>,
+    path_id: u32,
+    vertex_start: usize,
+    index_start: usize,
+    error: Option<CapacityError>,
+}
+
+impl<'l, 'a> LayerBuilder<'l, 'a> {
+    fn new(layer: &'l mut LayerMut<'a>, gradient: Option<&'l Gradient>, path_id: u32) -> Self {
+        Self {
+            vertex_start: layer.vertices_len(),
+            index_start: layer.indices_len(),
+            layer,
+            gradient,
+            path_id,
+            error: None,
+        }
+    }
+
+    fn add_vertex(
+        &mut self,
+        position: Vec2,
+        normal: Vec2,
+        advancement: f32,
+    ) -> Result<VertexId, GeometryBuilderError> {
+        let id = self.layer.vertices_len();
+        let attributes = Attributes::new(normal, advancement, self.path_id);
+        let color = paint(self.gradient, position);
+        match self
+            .layer
+            .extend_vertex_data(once((position, Vec2::ZERO, attributes, color)))
+        {
+            Ok(_) => Ok(VertexId(id as _)),
+            Err(error) => {
+                self.error.get_or_insert(error);
+                Err(GeometryBuilderError::TooManyVertices)
+            }
+        }
+    }
+
+    fn finish(self, result: TessellationResult) -> Result<Shape, TessellationError> {
+        let error = match (self.error, result) {
+            (Some(error), _) => error.into(),
+            (None, Err(error)) => error.into(),
+            (None, Ok(_)) => {
+                return Ok(Shape {
+                    vertices: self.vertex_start..self.layer.vertices_len(),
+                    indices: self.index_start..self.layer.indices_len(),
+                    path_id: self.path_id,
+                })
+            }
+        };
+        self.layer.truncate_vertices(self.vertex_start);
+        self.layer.truncate_indices(self.index_start);
+        Err(error)
+    }
+}
+
+impl GeometryBuilder for LayerBuilder<'_, '_> {
+    fn begin_geometry(&mut self) {}
+
+    fn end_geometry(&mut self) -> Count {
+        Count {
+            vertices: (self.layer.vertices_len() - self.vertex_start) as _,
+            indices: (self.layer.indices_len() - self.index_start) as _,
+        }
+    }
+
+    fn add_triangle(&mut self, a: VertexId, b: VertexId, c: VertexId) {
+        if self.error.is_none() {
+            if let Err(error) = self.layer.extend_indices([a, b, c].map(|id| id.0 as Index)) {
+                self.error = Some(error);
+            }
+        }
+    }
+
+    fn abort_geometry(&mut self) {
+        self.layer.truncate_vertices(self.vertex_start);
+        self.layer.truncate_indices(self.index_start);
+    }
+}
+
+impl FillGeometryBuilder for LayerBuilder<'_, '_> {
+    fn add_fill_vertex(&mut self, vertex: FillVertex) -> Result<VertexId, GeometryBuilderError> {
+        self.add_vertex(mint_convert(vertex.position()), Vec2::ZERO, 0.0)
     }
 }
 
+impl StrokeGeometryBuilder for LayerBuilder<'_, '_> {
+    fn add_stroke_vertex(
+        &mut self,
+        vertex: StrokeVertex,
+    ) -> Result<VertexId, GeometryBuilderError> {
+        let normal = vertex.normal();
+        self.add_vertex(
+            mint_convert(vertex.position()),
+            vec2(normal.x, normal.y),
+            vertex.advancement(),
+        )
+    }
+}
+
+fn paint(gradient: Option<&Gradient>, position: Vec2) -> Color {
+    gradient.map_or(Color::WHITE, |gradient| gradient.color_at(position))
+}
+
+fn midpoint(a: Attributes, b: Attributes) -> Attributes {
+    Attributes::new(
+        (a.normal + b.normal) * 0.5,
+        (a.advancement + b.advancement) * 0.5,
+        a.path_id,
+    )
+}
+
+fn subdivide(
+    positions: &mut Vec<Vec2>,
+    triangles: &mut Vec<[u32; 3]>,
+    gradient: &Gradient,
+) -> Vec<[usize; 2]> {
+    let mut splits = Vec::new();
+    let mut midpoints = HashMap::new();
+    let edges = |triangle: [u32; 3]| {
+        [0, 1, 2].map(|i| {
+            let (a, b) = (triangle[i] as usize, triangle[(i + 1) % 3] as usize);
+            [a.min(b), a.max(b)]
+        })
+    };
+    for _ in 0..MAX_SUBDIVISIONS {
+        midpoints.clear();
+        for &triangle in triangles.iter() {
+            for key in edges(triangle) {
+                midpoints
+                    .entry(key)
+                    .or_insert_with(|| gradient.split(positions[key[0]], positions[key[1]]));
+            }
+            let corners = triangle.map(|i| positions[i as usize]);
+            if edges(triangle).iter().all(|key| midpoints[key].is_none())
+                && !gradient.is_linear(corners)
+            {
+                let longest = edges(triangle)
+                    .into_iter()
+                    .max_by(|a, b| {
+                        let length = |[a, b]: [usize; 2]| positions[a].distance(positions[b]);
+                        length(*a).total_cmp(&length(*b))
+                    })
+                    .unwrap();
+                midpoints.insert(
+                    longest,
+                    Some((positions[longest[0]] + positions[longest[1]]) * 0.5),
+                );
+            }
+        }
+        let mut created = HashMap::new();
+        let mut next = Vec::with_capacity(triangles.len());
+        for &triangle in triangles.iter() {
+            let mids = edges(triangle).map(|key| {
+                let position = midpoints[&key]?;
+                if let Some(&mid) = created.get(&key) {
+                    return Some(mid);
+                }
+                if splits.len() >= MAX_SPLITS {
+                    return None;
+                }
+                positions.push(position);
+                splits.push(key);
+                let mid = positions.len() as u32 - 1;
+                created.insert(key, mid);
+                Some(mid)
+            });
+            let rotation = match mids.iter().flatten().count() {
+                0 => {
+                    next.push(triangle);
+                    continue;
+                }
+                1 => mids.iter().position(Option::is_some).unwrap(),
+                2 => (mids.iter().position(Option::is_none).unwrap() + 1) % 3,
+                _ => 0,
+            };
+            let [v0, v1, v2] = [0, 1, 2].map(|i| triangle[(i + rotation) % 3]);
+            match [0, 1, 2].map(|i| mids[(i + rotation) % 3]) {
+                [Some(m0), None, None] => next.extend([[v0, m0, v2], [m0, v1, v2]]),
+                [Some(m0), Some(m1), None] => {
+                    next.extend([[m0, v1, m1], [v0, m0, m1], [v0, m1, v2]])
+                }
+                [Some(m0), Some(m1), Some(m2)] => {
+                    next.extend([[v0, m0, m2], [m0, v1, m1], [m2, m1, v2], [m0, m1, m2]])
+                }
+                _ => unreachable!(),
+            }
+        }
+        if next.len() == triangles.len() {
+            break;
+        }
+        *triangles = next;
+    }
+    splits
+}
+
 fn mint_convert<P: From<mint::Point2<f32>>>(p: impl Into<mint::Point2<f32>>) -> P {
     p.into().into()
 }
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::layers::{color, layer_bounds},
+    };
+
+    fn square(half: f32) -> (Vec<Vec2>, Vec<[u32; 3]>) {
+        let positions = vec![
+            vec2(-half, -half),
+            vec2(half, -half),
+            vec2(half, half),
+            vec2(-half, half),
+        ];
+        (positions, vec![[0, 1, 2], [0, 2, 3]])
+    }
+
+    fn black_to_white() -> Vec<(f32, Color)> {
+        vec![(0.0, color(0, 0, 0, 255)), (1.0, color(255, 255, 255, 255))]
+    }
+
+    #[test]
+    fn subdivide_keeps_linear_colors() {
+        let gradient = Gradient::linear(vec2(-100.0, 0.0), vec2(100.0, 0.0), black_to_white());
+        let (mut positions, mut triangles) = square(100.0);
+        assert!(subdivide(&mut positions, &mut triangles, &gradient).is_empty());
+        assert_eq!(triangles.len(), 2);
+    }
+
+    #[test]
+    fn subdivide_skips_constant_colors() {
+        let gradient = Gradient::radial(vec2(1000.0, 1000.0), 10.0, black_to_white());
+        let (mut positions, mut triangles) = square(100.0);
+        assert!(subdivide(&mut positions, &mut triangles, &gradient).is_empty());
+    }
+
+    #[test]
+    fn subdivide_splits_on_stops() {
+        let stops = [
+            (0.0, color(0, 0, 0, 255)),
+            (0.25, color(255, 0, 0, 255)),
+            (1.0, color(255, 255, 255, 255)),
+        ];
+        let gradient = Gradient::linear(vec2(-100.0, 0.0), vec2(100.0, 0.0), stops);
+        let (mut positions, mut triangles) = square(100.0);
+        let splits = subdivide(&mut positions, &mut triangles, &gradient);
+        assert!(!splits.is_empty());
+        for &position in &positions[4..] {
+            assert!(
+                (gradient.offset(position) - 0.25).abs() < 0.001,
+                "{}",
+                position
+            );
+        }
+        let area = |[a, b, c]: [u32; 3]| {
+            let [a, b, c] = [a, b, c].map(|i| positions[i as usize]);
+            (b - a).perp_dot(c - a).abs() * 0.5
+        };
+        let total: f32 = triangles.iter().map(|&triangle| area(triangle)).sum();
+        assert!((total - 200.0 * 200.0).abs() < 1.0, "{}", total);
+    }
+
+    #[test]
+    fn subdivide_is_bounded() {
+        let gradient = Gradient::radial(vec2(3.0, -7.0), 10.0, black_to_white());
+        let (mut positions, mut triangles) = square(1000.0);
+        let splits = subdivide(&mut positions, &mut triangles, &gradient);
+        assert!(!splits.is_empty());
+        assert!(splits.len() <= MAX_SPLITS);
+        assert_eq!(positions.len(), 4 + splits.len());
+        assert!(triangles
+            .iter()
+            .flatten()
+            .all(|&i| (i as usize) < positions.len()));
+    }
+
+    #[test]
+    fn fits_in_counts_growth() {
+        let mut layers = Layers::new(&[layer_bounds(4, 3, 0)]);
+        let mut layer = layers.get_mut(0).unwrap();
+        layer.extend_vertices([Vec2::ZERO; 3]).unwrap();
+        let path = tessellation_chain(4).circle(Vec2::ZERO, 10.0).finish();
+        let mut tessellator = Tessellator::new();
+        tessellator.fill(path.iter()).unwrap();
+        assert!(tessellator.vertices().len() > layer.max_vertices_len());
+        assert!(tessellator.fits_in(&layer).is_ok());
+    }
+
+    #[test]
+    #[cfg(not(feature = "u32_indices"))]
+    fn fits_in_reports_everything_dropped() {
+        let mut layers = Layers::new(&[layer_bounds(Layers::MAX_VERTICES as _, 3, 0)]);
+        let mut layer = layers.get_mut(0).unwrap();
+        layer
+            .extend_vertices(vec![Vec2::ZERO; Layers::MAX_VERTICES - 4])
+            .unwrap();
+        let path = tessellation_chain(4).circle(Vec2::ZERO, 10.0).finish();
+        let mut tessellator = Tessellator::new();
+        tessellator.fill(path.iter()).unwrap();
+        let error = tessellator.fits_in(&layer).unwrap_err();
+        assert_eq!(error.buffer, LayerBuffer::Vertices);
+        assert_eq!(error.written, 0);
+        assert_eq!(error.dropped, Some(tessellator.vertices().len()));
+    }
+}
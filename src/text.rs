@@ -0,0 +1,266 @@
+use {
+    glam::{vec2, Vec2},
+    lyon_tessellation::{
+        math::{point, Point},
+        path::{Path, PathEvent},
+    },
+    std::{fs, io, path::Path as FilePath},
+    ttf_parser::{Face, GlyphId, OutlineBuilder},
+};
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub enum Align {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+pub struct Font {
+    data: Vec<u8>,
+    units_per_em: f32,
+    ascender: f32,
+    descender: f32,
+    line_gap: f32,
+}
+
+impl Font {
+    pub fn from_bytes(data: impl Into<Vec<u8>>) -> Option<Self> {
+        let data = data.into();
+        let face = Face::from_slice(&data, 0).ok()?;
+        let units_per_em = face.units_per_em() as f32;
+        let ascender = face.ascender() as f32;
+        let descender = face.descender() as f32;
+        let line_gap = face.line_gap() as f32;
+        Some(Self {
+            data,
+            units_per_em,
+            ascender,
+            descender,
+            line_gap,
+        })
+    }
+
+    pub fn load(path: impl AsRef<FilePath>) -> io::Result<Self> {
+        Self::from_bytes(fs::read(path)?)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "unsupported font"))
+    }
+
+    pub fn ascender(&self, size: f32) -> f32 {
+        self.ascender * size / self.units_per_em
+    }
+
+    pub fn descender(&self, size: f32) -> f32 {
+        self.descender * size / self.units_per_em
+    }
+
+    pub fn line_height(&self, size: f32) -> f32 {
+        (self.ascender - self.descender + self.line_gap) * size / self.units_per_em
+    }
+
+    pub fn measure(&self, text: &str, size: f32) -> Vec2 {
+        let face = self.face();
+        let scale = size / self.units_per_em;
+        let mut width = 0.0f32;
+        let mut lines = 0;
+        for line in text.lines() {
+            width = width.max(advance(&face, line, |_, _| {}) * scale);
+            lines += 1;
+        }
+        vec2(width, lines as f32 * self.line_height(size))
+    }
+
+    pub fn path(&self, text: &str, size: f32, align: Align) -> Path {
+        let mut events = Vec::new();
+        self.events(text, size, align, &mut events);
+        let mut builder = Path::builder();
+        for event in events {
+            builder.path_event(event);
+        }
+        builder.build()
+    }
+
+    pub(crate) fn events(&self, text: &str, size: f32, align: Align, events: &mut Vec<PathEvent>) {
+        let face = self.face();
+        let scale = size / self.units_per_em;
+        let mut outline = Outline {
+            events,
+            offset: Vec2::ZERO,
+            scale,
+            first: point(0.0, 0.0),
+            current: point(0.0, 0.0),
+            open: false,
+        };
+        for (i, line) in text.lines().enumerate() {
+            let width = advance(&face, line, |_, _| {}) * scale;
+            let x = match align {
+                Align::Left => 0.0,
+                Align::Center => -width * 0.5,
+                Align::Right => -width,
+            };
+            let y = -(i as f32) * self.line_height(size);
+            advance(&face, line, |glyph, pen| {
+                outline.offset = vec2(x + pen * scale, y);
+                face.outline_glyph(glyph, &mut outline);
+                outline.end(false);
+            });
+        }
+    }
+
+    fn face(&self) -> Face<'_> {
+        Face::from_slice(&self.data, 0).unwrap()
+    }
+}
+
+fn advance(face: &Face<'_>, line: &str, mut f: impl FnMut(GlyphId, f32)) -> f32 {
+    let mut pen = 0.0;
+    let mut prev = None;
+    for c in line.chars() {
+        let glyph = face.glyph_index(c).unwrap_or(GlyphId(0));
+        if let Some(prev) = prev {
+            pen += kerning(face, prev, glyph) as f32;
+        }
+        f(glyph, pen);
+        pen += face.glyph_hor_advance(glyph).unwrap_or(0) as f32;
+        prev = Some(glyph);
+    }
+    pen
+}
+
+fn kerning(face: &Face<'_>, left: GlyphId, right: GlyphId) -> i16 {
+    face.tables()
+        .kern
+        .and_then(|kern| {
+            kern.subtables
+                .into_iter()
+                .filter(|subtable| subtable.horizontal && !subtable.variable)
+                .find_map(|subtable| subtable.glyphs_kerning(left, right))
+        })
+        .unwrap_or(0)
+}
+
+struct Outline<'a> {
+    events: &'a mut Vec<PathEvent>,
+    offset: Vec2,
+    scale: f32,
+    first: Point,
+    current: Point,
+    open: bool,
+}
+
+impl Outline<'_> {
+    fn point(&self, x: f32, y: f32) -> Point {
+        let p = self.offset + vec2(x, y) * self.scale;
+        point(p.x, p.y)
+    }
+
+    fn end(&mut self, close: bool) {
+        if self.open {
+            self.events.push(PathEvent::End {
+                last: self.current,
+                first: self.first,
+                close,
+            });
+            self.current = self.first;
+            self.open = false;
+        }
+    }
+}
+
+impl OutlineBuilder for Outline<'_> {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.end(false);
+        let at = self.point(x, y);
+        self.events.push(PathEvent::Begin { at });
+        self.first = at;
+        self.current = at;
+        self.open = true;
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        let to = self.point(x, y);
+        self.events.push(PathEvent::Line {
+            from: self.current,
+            to,
+        });
+        self.current = to;
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let (ctrl, to) = (self.point(x1, y1), self.point(x, y));
+        self.events.push(PathEvent::Quadratic {
+            from: self.current,
+            ctrl,
+            to,
+        });
+        self.current = to;
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let (ctrl1, ctrl2, to) = (self.point(x1, y1), self.point(x2, y2), self.point(x, y));
+        self.events.push(PathEvent::Cubic {
+            from: self.current,
+            ctrl1,
+            ctrl2,
+            to,
+        });
+        self.current = to;
+    }
+
+    fn close(&mut self) {
+        self.end(true);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn font() -> Font {
+        Font::from_bytes(&include_bytes!("../tests/fonts/KetupaBlocks-Regular.ttf")[..]).unwrap()
+    }
+
+    fn x_bounds(path: &Path) -> (f32, f32) {
+        path.iter()
+            .flat_map(|event| [event.from(), event.to()])
+            .fold((f32::MAX, f32::MIN), |(min, max), p| {
+                (min.min(p.x), max.max(p.x))
+            })
+    }
+
+    #[test]
+    fn metrics_scale_with_size() {
+        let font = font();
+        assert_eq!(font.ascender(10.0), 8.0);
+        assert_eq!(font.descender(10.0), -2.0);
+        assert_eq!(font.line_height(10.0), 12.0);
+    }
+
+    #[test]
+    fn measure_kerns_and_counts_lines() {
+        let font = font();
+        assert_eq!(font.measure("HI", 10.0), vec2(9.0, 12.0));
+        assert_eq!(font.measure("TI", 10.0), vec2(8.5, 12.0));
+        assert_eq!(font.measure("H\nTI", 10.0), vec2(8.5, 24.0));
+        assert_eq!(font.measure("", 10.0), Vec2::ZERO);
+    }
+
+    #[test]
+    fn align_offsets_lines() {
+        let font = font();
+        assert_eq!(x_bounds(&font.path("H", 10.0, Align::Left)), (0.5, 5.5));
+        assert_eq!(x_bounds(&font.path("H", 10.0, Align::Center)), (-2.5, 2.5));
+        assert_eq!(x_bounds(&font.path("H", 10.0, Align::Right)), (-5.5, -0.5));
+    }
+
+    #[test]
+    fn lines_go_down() {
+        let font = font();
+        let path = font.path("I\nI", 10.0, Align::Left);
+        let ys = path.iter().map(|event| event.to().y);
+        let (min, max) = ys.fold((f32::MAX, f32::MIN), |(min, max), y| {
+            (min.min(y), max.max(y))
+        });
+        assert_eq!((min, max), (-12.0, 7.0));
+    }
+}
@@ -0,0 +1,192 @@
+use ketupa_demo_engine::{
+    color, instance, layer_bounds, snapshot, stroke_style, tessellation_chain, transform, vec2,
+    Align, Backend, BlendMode, Font, Gradient, Headless, Layers, LineCap, Mouse, Space, Vec2,
+};
+
+#[test]
+fn fill() {
+    snapshot(200, 200, &[layer_bounds(16, 16, 1)], 1)
+        .with_backend(Backend::Raster)
+        .assert_matches(
+            "tests/snapshots/fill.png",
+            |_| {},
+            |st| {
+                st.screen.set_clear_color(color(235, 64, 52, 255));
+                let path = tessellation_chain(5)
+                    .chain([
+                        vec2(-60.0, 12.0),
+                        vec2(-22.0, 92.0),
+                        vec2(40.0, 67.0),
+                        vec2(52.0, -45.0),
+                        vec2(-10.0, -92.0),
+                    ])
+                    .finish();
+                let mut layer = st.layers.get_mut(0).unwrap();
+                st.tessellator.fill_into(&mut layer, path.iter()).unwrap();
+                layer
+                    .set_instances([instance(Default::default(), color(252, 186, 3, 255))])
+                    .unwrap();
+            },
+        )
+}
+
+#[test]
+fn click() {
+    snapshot(200, 200, &[layer_bounds(8, 8, 1)], 2)
+        .with_backend(Backend::Raster)
+        .assert_matches(
+            "tests/snapshots/click.png",
+            |sc| {
+                if sc.frame == 1 {
+                    sc.mouse.move_to(vec2(20.0, 20.0));
+                    sc.mouse.press(Mouse::LEFT);
+                }
+            },
+            |st| {
+                let mut layer = st.layers.get_mut(0).unwrap();
+                layer
+                    .set_vertices([vec2(-80.0, -80.0), vec2(0.0, 80.0), vec2(80.0, -80.0)])
+                    .unwrap();
+                layer.set_indices([0, 1, 2]).unwrap();
+                let c = if st.mouse.is_pressed(Mouse::LEFT) {
+                    255
+                } else {
+                    0
+                };
+                layer
+                    .set_instances([instance(Default::default(), color(c, 0, 0, 255))])
+                    .unwrap();
+            },
+        )
+}
+
+#[test]
+fn gradient() {
+    snapshot(200, 200, &[layer_bounds(64, 64, 1).with_colors()], 1)
+        .with_backend(Backend::Raster)
+        .with_tolerance(1)
+        .assert_matches(
+            "tests/snapshots/gradient.png",
+            |_| {},
+            |st| {
+                let path = tessellation_chain(4)
+                    .rounded_rect(vec2(-80.0, -80.0), vec2(80.0, 80.0), 20.0)
+                    .finish();
+                st.tessellator.set_gradient(Some(Gradient::radial(
+                    vec2(-20.0, 20.0),
+                    100.0,
+                    [
+                        (0.0, color(255, 255, 255, 255)),
+                        (0.5, color(52, 120, 235, 255)),
+                        (1.0, color(20, 20, 60, 255)),
+                    ],
+                )));
+                let mut layer = st.layers.get_mut(0).unwrap();
+                st.tessellator.fill_into(&mut layer, path.iter()).unwrap();
+                layer
+                    .set_instances([instance(Default::default(), color(255, 255, 255, 255))])
+                    .unwrap();
+            },
+        )
+}
+
+#[test]
+fn dashed_stroke() {
+    snapshot(200, 200, &[layer_bounds(64, 64, 1)], 1)
+        .with_backend(Backend::Raster)
+        .with_samples(4)
+        .with_tolerance(1)
+        .assert_matches(
+            "tests/snapshots/dashed_stroke.png",
+            |_| {},
+            |st| {
+                let path = tessellation_chain(4).circle(vec2(0.0, 0.0), 70.0).finish();
+                let style = stroke_style(8.0)
+                    .with_cap(LineCap::Round)
+                    .with_dashes([20.0, 12.0]);
+                let mut layer = st.layers.get_mut(0).unwrap();
+                st.tessellator
+                    .stroke_styled_into(&mut layer, path.iter(), &style)
+                    .unwrap();
+                layer
+                    .set_instances([instance(Default::default(), color(0, 0, 0, 255))])
+                    .unwrap();
+            },
+        )
+}
+
+#[test]
+fn text() {
+    let font = Font::load("tests/fonts/KetupaBlocks-Regular.ttf").unwrap();
+    snapshot(200, 200, &[layer_bounds(64, 64, 1)], 1)
+        .with_backend(Backend::Raster)
+        .with_samples(4)
+        .with_tolerance(1)
+        .assert_matches(
+            "tests/snapshots/text.png",
+            |_| {},
+            |st| {
+                let mut layer = st.layers.get_mut(0).unwrap();
+                st.tessellator
+                    .text_into(&mut layer, &font, "HIT\nTI", 60.0, Align::Center)
+                    .unwrap();
+                layer
+                    .set_instances([instance(Default::default(), color(0, 0, 0, 255))])
+                    .unwrap();
+            },
+        )
+}
+
+fn assert_layers(backend: Backend, tolerance: u8) {
+    let bounds = [
+        layer_bounds(4, 6, 1).with_space(Space::Screen),
+        layer_bounds(4, 6, 2).with_blend(BlendMode::Multiply),
+        layer_bounds(4, 6, 1),
+    ];
+    snapshot(200, 200, &bounds, 1)
+        .with_backend(backend)
+        .with_tolerance(tolerance)
+        .assert_matches(
+            "tests/snapshots/layers.png",
+            |_| {},
+            |st| {
+                st.screen.set_clear_color(color(30, 30, 30, 255));
+                st.screen.set_zoom(0.5);
+                let colors = [
+                    color(255, 255, 255, 255),
+                    color(255, 200, 0, 255),
+                    color(40, 120, 255, 255),
+                ];
+                for (index, color) in colors.into_iter().enumerate() {
+                    let mut layer = st.layers.get_mut(index).unwrap();
+                    if layer.vertices_len() == 0 {
+                        layer.extend_quad(vec2(60.0, 60.0)).unwrap();
+                    }
+                    if index == 0 {
+                        layer.set_opacity(0.5);
+                    }
+                    let instances = (0..layer.max_instances_len()).map(|i| {
+                        let offset = (index + 2 * i) as f32 - 1.0;
+                        let translation = vec2(offset * 30.0, offset * -15.0);
+                        instance(transform(Vec2::ONE, 0.0, translation), color)
+                    });
+                    layer.set_instances(instances).unwrap();
+                }
+            },
+        )
+}
+
+#[test]
+fn layers() {
+    assert_layers(Backend::Raster, 1);
+}
+
+// The GPU rounds sRGB differently from `Raster`, 2 levels cover it.
+#[test]
+fn layers_headless() {
+    if Headless::new(&Layers::new(&[])).is_none() {
+        eprintln!("skipping layers_headless, no wgpu adapter");
+        return;
+    }
+    assert_layers(Backend::Headless, 2);
+}